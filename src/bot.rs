@@ -1,179 +1,667 @@
-use super::map::NystopiaMap;
-use super::ProgArgs;
+use super::map::{NystopiaMap, Signal};
+use super::nekode::{self, NekoDirs, NekoOps};
+use super::species::BotSpecies;
+use super::rng::SimRng;
+use super::settings::Settings;
 use nanorand::Rng;
 use log::info;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NekobotState {
     Wander,
     Forage,
     Dead,
 }
 
+/// What a bot feeds on
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NekobotKind {
+    /// Eats plants and scavenges carcasses
+    #[default]
+    Herbivore,
+    /// Only gets energy by catching herbivores
+    Carnivore,
+}
+
+/// What happens when a bot steps onto a tile another living bot stands on
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// Any number of bots can share a tile
+    Shared,
+    /// The step fails
+    Block,
+    /// The two bots trade places
+    Swap,
+    /// The other bot gets pushed one tile further, if there's room
+    Push,
+}
+
+/// A tile the bot saw food on, and when
+#[derive(Clone, Serialize, Deserialize)]
+struct FoodMemory {
+    row: u16,
+    col: u16,
+    seen_at: u64,
+}
+
+/// Which bots count as kin, and so can be given energy
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Kin {
+    /// Any bot
+    Off,
+    /// Bots of the same species
+    Species,
+    /// Bots descending from the same founder
+    Lineage,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Nekobot {
-    label: String,
+    // Number identifying the bot for the whole run
+    id: u32,
+    // Glyph of the bot's species it is drawn with
+    glyph: char,
+    // Index of the bot's species
+    species: usize,
+    // Founder of the bot's line of descent
+    lineage: u32,
+    kind: NekobotKind,
+    color: Option<(u8, u8, u8)>,
     row: u16,
     col: u16,
     energy: u8,
     sight: u16,
     see_food_move_score: u64,
+    scent_move_score: u64,
+    signal_move_score: u64,
+    recall_move_score: u64,
     move_score: u64,
     state: NekobotState,
+    // Ticks since the bot died
+    dead_ticks: u16,
+    // Ticks lived so far
+    age: u64,
     hungry_threshold: u8,
     nekode: Vec<NekoOps>,
+    // Tile of the food the bot is going for this tick, until contests are settled
+    claim: Option<(u16, u16)>,
+    collision: Collision,
+    // How many times the program runs each tick
+    speed: u8,
+    // Energy gained from each bot caught (carnivores only)
+    prey_energy: u8,
+    // Share of the energy lost when giving it to another bot, in percent
+    give_loss: u8,
+    kin: Kin,
+    // Food tiles seen lately, up to `memory_size` of them, trusted less and less until
+    // they're forgotten after `memory_span` ticks
+    memory: Vec<FoodMemory>,
+    memory_size: u16,
+    memory_span: u16,
+    // How well each plant species is digested, as a multiplier of its energy. These
+    // average out to 1, so a bot can be a generalist or specialize in some plants at
+    // the expense of others.
+    digestion: Vec<f32>,
 }
 
-#[derive(Clone)]
-enum NekoDirs {
-    Here,
-    Up,
-    Down,
-    Left,
-    Right,
-}
+impl Nekobot {
+    pub fn new_rand(
+        id: u32,
+        species_id: usize,
+        species: &BotSpecies,
+        map: &NystopiaMap,
+        others: &[Nekobot],
+        settings: &Settings,
+        rng: &mut SimRng,
+    ) -> Self {
+
+        let plants = map.get_plants().len();
+        let mut digestion: Vec<f32> = (0..plants)
+            .map(|_| (rng.generate::<u8>() % 100 + 1) as f32)
+            .collect();
+        Self::normalize(&mut digestion);
+
+        let (row, col) = Self::spawn_point(map, others, settings, rng);
+
+        // Sense every direction of the neighborhood, then move and feed
+        let nekode = match &species.program {
+            Some(program) => program.0.clone(),
+            None => {
+                let mut nekode = vec![NekoOps::Sense(NekoDirs::Here)];
+                for dir in settings.neighborhood.dirs() {
+                    nekode.push(NekoOps::Sense(dir.clone()));
+                }
+                match species.kind {
+                    NekobotKind::Herbivore => nekode.extend([NekoOps::Move, NekoOps::Eat, NekoOps::Scavenge, NekoOps::Halt]),
+                    NekobotKind::Carnivore => nekode.extend([NekoOps::Move, NekoOps::Hunt, NekoOps::Halt]),
+                }
+                nekode
+            }
+        };
+
+        // Anything the species leaves open comes from the command line
+        let (sight, speed, hunger) = match species.kind {
+            NekobotKind::Herbivore => (settings.sight, 1, 80),
+            NekobotKind::Carnivore => (settings.predator_sight, settings.predator_speed, settings.predator_hunger),
+        };
+        let memory_size = settings.memory_size;
+        let sight = Self::roll_u16(species.sight.unwrap_or((sight, sight)), rng);
+        let memory_size = Self::roll_u16(species.memory.unwrap_or((memory_size, memory_size)), rng);
+        let speed = Self::roll_u8(species.speed.unwrap_or((speed, speed)), rng);
+        let hunger = Self::roll_u8(species.hunger.unwrap_or((hunger, hunger)), rng);
 
-impl std::fmt::Display for NekoDirs {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Here => write!(f, "Here"),
-            Self::Up => write!(f, "Up"),
-            Self::Down => write!(f, "Down"),
-            Self::Left => write!(f, "Left"),
-            Self::Right => write!(f, "Right"),
+        Self {
+            row,
+            col,
+            id,
+            glyph: species.glyph(id as usize),
+            species: species_id,
+            // Every new bot founds a lineage of its own
+            lineage: id,
+            kind: species.kind,
+            color: species.color,
+            energy: rng.generate::<u8>() % 90 + 10,
+            sight: sight.max(1),
+            state: NekobotState::Wander,
+            dead_ticks: 0,
+            age: 0,
+            see_food_move_score: 200000,
+            scent_move_score: 1000,
+            signal_move_score: 1000,
+            recall_move_score: 100000,
+            move_score: 100,
+            hungry_threshold: hunger,
+            nekode,
+            claim: None,
+            collision: settings.collision,
+            speed: speed.max(1),
+            prey_energy: settings.prey_energy,
+            give_loss: settings.give_loss.min(100),
+            kin: settings.kin,
+            memory: vec![],
+            memory_size,
+            memory_span: settings.memory_span.max(1),
+            digestion,
         }
     }
-}
 
-#[derive(Clone)]
-enum NekoOps {
-    Sense(NekoDirs),
-    Move,
-    Eat,
-    Halt,
-}
+    /// A mutated copy of the bot, with fresh energy and a slightly different program, sight,
+    /// hunger and digestion, spawned somewhere else on the map. Its glyph is the one `species`
+    /// gives its new id.
+    pub fn mutant(
+        &self,
+        id: u32,
+        species: &BotSpecies,
+        map: &NystopiaMap,
+        others: &[Nekobot],
+        settings: &Settings,
+        rng: &mut SimRng,
+    ) -> Self {
+        let mut nekode = self.nekode.clone();
+        nekode::mutate(&mut nekode, map.get_neighborhood().dirs(), rng);
+        // Each plant is digested up to a tenth better or worse than by the parent
+        let mut digestion: Vec<f32> = self
+            .digestion
+            .iter()
+            .map(|d| d * (rng.generate::<u8>() % 21 + 90) as f32 / 100.0)
+            .collect();
+        Self::normalize(&mut digestion);
+        let (row, col) = Self::spawn_point(map, others, settings, rng);
 
-impl Nekobot {
-    pub fn new_rand(label: &str, rows: u16, cols: u16, prog_args: &ProgArgs) -> Self {
-        let mut rng = nanorand::tls_rng();
         Self {
-            row: rng.generate::<u16>() % rows,
-            col: rng.generate::<u16>() % cols,
-            label: label.into(),
+            id,
+            glyph: species.glyph(id as usize),
+            row,
+            col,
             energy: rng.generate::<u8>() % 90 + 10,
-            sight: prog_args.sight,
+            sight: Self::roll_u16((self.sight.saturating_sub(1), self.sight.saturating_add(1)), rng).max(1),
             state: NekobotState::Wander,
-            see_food_move_score: 200000,
-            move_score: 100,
-            hungry_threshold: 80,
-            nekode: vec![
-                NekoOps::Sense(NekoDirs::Here),
-                NekoOps::Sense(NekoDirs::Up),
-                NekoOps::Sense(NekoDirs::Right),
-                NekoOps::Sense(NekoDirs::Down),
-                NekoOps::Sense(NekoDirs::Left),
-                NekoOps::Move,
-                NekoOps::Eat,
-                NekoOps::Halt,
-            ]
+            dead_ticks: 0,
+            age: 0,
+            hungry_threshold: Self::roll_u8((self.hungry_threshold.saturating_sub(5), self.hungry_threshold.saturating_add(5)), rng),
+            nekode,
+            claim: None,
+            memory: vec![],
+            digestion,
+            ..*self
+        }
+    }
+
+    /// Somewhere a new bot can actually stand, and a tile of its own unless bots can
+    /// share them
+    fn spawn_point(map: &NystopiaMap, others: &[Nekobot], settings: &Settings, rng: &mut SimRng) -> (u16, u16) {
+        let (mut row, mut col) = (0, 0);
+        for _ in 0..1000 {
+            row = rng.generate::<u16>() % map.get_rows();
+            col = rng.generate::<u16>() % map.get_cols();
+            let taken = settings.collision != Collision::Shared
+                && others.iter().any(|bot| bot.is_alive() && bot.row == row && bot.col == col);
+            if map.passable(row, col) && !taken {
+                break;
+            }
+        }
+        (row, col)
+    }
+
+    /// Moves the bot to a free tile along the edges of the map, where it can stand
+    pub fn move_to_edge(&mut self, map: &NystopiaMap, others: &[Nekobot], settings: &Settings, rng: &mut SimRng) {
+        let (rows, cols) = (map.get_rows(), map.get_cols());
+        for _ in 0..1000 {
+            let (row, col) = match rng.generate::<u8>() % 4 {
+                0 => (0, rng.generate::<u16>() % cols),
+                1 => (rows - 1, rng.generate::<u16>() % cols),
+                2 => (rng.generate::<u16>() % rows, 0),
+                _ => (rng.generate::<u16>() % rows, cols - 1),
+            };
+            let taken = settings.collision != Collision::Shared
+                && others.iter().any(|bot| bot.is_alive() && bot.row == row && bot.col == col);
+            if map.passable(row, col) && !taken {
+                (self.row, self.col) = (row, col);
+                return;
+            }
+        }
+    }
+
+    /// A random value within the inclusive range `(min, max)`
+    fn roll_u16((min, max): (u16, u16), rng: &mut SimRng) -> u16 {
+        let (min, max) = (min.min(max), min.max(max));
+        min + (rng.generate::<u32>() % ((max - min) as u32 + 1)) as u16
+    }
+
+    fn roll_u8((min, max): (u8, u8), rng: &mut SimRng) -> u8 {
+        Self::roll_u16((min as u16, max as u16), rng) as u8
+    }
+
+    /// Scales digestion multipliers so that they average out to 1
+    fn normalize(digestion: &mut [f32]) {
+        let scale = digestion.len() as f32 / digestion.iter().sum::<f32>();
+        for d in digestion.iter_mut() {
+            *d *= scale;
         }
     }
 
-    pub fn get_state(self: &Self) -> NekobotState {
+    pub fn get_species(&self) -> usize {
+        self.species
+    }
+
+    pub fn get_color(&self) -> Option<(u8, u8, u8)> {
+        self.color
+    }
+
+    pub fn get_kind(&self) -> NekobotKind {
+        self.kind
+    }
+
+    pub fn get_state(&self) -> NekobotState {
         self.state
     }
 
-    fn op_sense(&self, membank: &mut Vec<u64>, map: &NystopiaMap, dir: &NekoDirs) {
+    pub fn is_alive(&self) -> bool {
+        !matches!(self.state, NekobotState::Dead)
+    }
+
+    /// Whether a step in `dir` is possible: it has to be part of the map's neighborhood,
+    /// lead onto a tile the bot can walk on and not be stopped by a bot standing there
+    fn can_move(&self, map: &NystopiaMap, others: &Others, dir: &NekoDirs) -> bool {
+        match map.neighbor(self.row, self.col, dir) {
+            Some((row, col)) => map.passable(row, col) && !self.in_the_way(map, others, (row, col), dir),
+            None => false,
+        }
+    }
+
+    /// Whether a bot standing on `tile` keeps this one from stepping there in `dir`
+    fn in_the_way(&self, map: &NystopiaMap, others: &Others, (row, col): (u16, u16), dir: &NekoDirs) -> bool {
+        if self.collision == Collision::Shared || !others.occupied(row, col) {
+            return false;
+        }
+        match self.collision {
+            Collision::Shared | Collision::Swap => false,
+            Collision::Block => true,
+            // The one being pushed needs a free tile to go to
+            Collision::Push => match map.neighbor(row, col, dir) {
+                Some((next_row, next_col)) => !map.passable(next_row, next_col) || others.occupied(next_row, next_col),
+                None => true,
+            },
+        }
+    }
+
+    fn op_sense(
+        &self,
+        membank: &mut Vec<(NekoDirs, u64)>,
+        map: &NystopiaMap,
+        others: &Others,
+        dir: &NekoDirs,
+        rng: &mut SimRng,
+    ) {
+        if !self.can_move(map, others, dir) {
+            return;
+        }
         if self.hungry() {
-            membank.push(self.forage2(map, dir));
+            membank.push((dir.clone(), self.forage2(map, others, dir, rng)));
         } else {
-            membank.push(self.move_score);
+            membank.push((dir.clone(), self.move_score));
         }
     }
 
-    fn op_move(&mut self, membank: &mut Vec<u64>, map: &NystopiaMap) {
-        let mut rng = nanorand::tls_rng();
+    fn op_sense_scent(&self, membank: &mut Vec<(NekoDirs, u64)>, map: &NystopiaMap, others: &Others, dir: &NekoDirs) {
+        if !self.can_move(map, others, dir) {
+            return;
+        }
+        // Favor directions where the scent gets stronger
+        let here = map.get_scent(self.row, self.col);
+        let there = match map.neighbor(self.row, self.col, dir) {
+            Some((row, col)) => map.get_scent(row, col),
+            None => here,
+        };
+        let gradient = (there - here).max(0.0) as f64;
+        membank.push((dir.clone(), self.move_score + (gradient * self.scent_move_score as f64).round() as u64));
+    }
 
-        // Since Vec works like a stack, these need to be in reverse-order compared to the
-        // list in self.nekode
-        let dirs = [
-            NekoDirs::Left,
-            NekoDirs::Down,
-            NekoDirs::Right,
-            NekoDirs::Up,
-            NekoDirs::Here,
-        ];
-        let max_score: u64 = membank.iter().sum();
-        let mut rnd_score: u64 = rng.generate::<u64>() % max_score;
-        info!("Max({}) Chose({}) Membank({})", max_score, rnd_score, membank.iter().map(|x| format!("{}", x)).collect::<Vec<String>>().join(","));
-        for v in dirs {
-            if let Some(next_score) = membank.pop() {
-                if rnd_score >= next_score {
-                    rnd_score -= next_score;
-                } else {
-                    info!("Moving: {}", v);
-                    self.move_it(&v, map);
-                    break;
+    fn op_listen(&self, membank: &mut Vec<(NekoDirs, u64)>, map: &NystopiaMap, others: &Others, channel: u16) {
+        // Head toward the loudest call on the channel, not counting the bot's own
+        let loudest = map
+            .get_signals()
+            .iter()
+            .filter(|signal| signal.channel == channel && signal.sender != self.id)
+            .map(|signal| (signal, map.signal_strength(signal, self.row, self.col)))
+            .filter(|&(_, strength)| strength > 0.0)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        let Some((signal, strength)) = loudest else {
+            return;
+        };
+        let dir = self.food_dir(map, (signal.row, signal.col));
+        if dir != NekoDirs::Here && self.can_move(map, others, &dir) {
+            membank.push((dir, self.move_score + (strength * self.signal_move_score as f64).round() as u64));
+        }
+    }
+
+    fn op_recall(&self, membank: &mut Vec<(NekoDirs, u64)>, map: &NystopiaMap, others: &Others, dir: &NekoDirs) {
+        if !self.can_move(map, others, dir) {
+            return;
+        }
+        if !self.hungry() {
+            membank.push((dir.clone(), self.move_score));
+            return;
+        }
+        // Head back to the remembered food most worth the trip: the more recently seen and
+        // the closer, the better
+        let here = (self.row, self.col);
+        let best = self
+            .memory
+            .iter()
+            .map(|memory| {
+                let confidence = self.confidence(memory, map.get_tick());
+                let worth = confidence / (1.0 + map.distance(here, (memory.row, memory.col)));
+                (memory, confidence, worth)
+            })
+            .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+        let score = match best {
+            Some((memory, confidence, _)) if self.food_dir(map, (memory.row, memory.col)) == *dir => {
+                self.move_score + (confidence * self.recall_move_score as f64).round() as u64
+            }
+            _ => self.move_score,
+        };
+        membank.push((dir.clone(), score));
+    }
+
+    /// How much a memory is still trusted: fully when just made, down to nothing once it's
+    /// `memory_span` ticks old
+    fn confidence(&self, memory: &FoodMemory, tick: u64) -> f64 {
+        let age = tick.saturating_sub(memory.seen_at) as f64;
+        (1.0 - age / self.memory_span as f64).max(0.0)
+    }
+
+    /// Takes note of the food in sight, and forgets about what's gone or too old
+    fn remember(&mut self, map: &NystopiaMap) {
+        let tick = map.get_tick();
+        for (row, col) in self.visible_tiles(map) {
+            let food = map.get_tile(row, col).is_some_and(|tile| tile.has_food() && !tile.eaten());
+            let known = self.memory.iter().position(|memory| (memory.row, memory.col) == (row, col));
+            match (food, known) {
+                (true, Some(i)) => self.memory[i].seen_at = tick,
+                (true, None) => self.memory.push(FoodMemory { row, col, seen_at: tick }),
+                (false, Some(i)) => {
+                    self.memory.remove(i);
                 }
+                (false, None) => {}
+            }
+        }
+        let span = self.memory_span as u64;
+        self.memory.retain(|memory| tick.saturating_sub(memory.seen_at) < span);
+        // Only the freshest memories are kept
+        self.memory.sort_by_key(|memory| std::cmp::Reverse(memory.seen_at));
+        self.memory.truncate(self.memory_size as usize);
+    }
+
+    fn op_move(&mut self, membank: &mut Vec<(NekoDirs, u64)>, map: &NystopiaMap, others: &mut Others, rng: &mut SimRng) {
+
+        let max_score: u64 = membank.iter().map(|(_, score)| score).sum();
+        if max_score == 0 {
+            membank.clear();
+            return;
+        }
+        let mut rnd_score: u64 = rng.generate::<u64>() % max_score;
+        info!("Max({}) Chose({}) Membank({})", max_score, rnd_score, membank.iter().map(|(dir, score)| format!("{}:{}", dir, score)).collect::<Vec<String>>().join(","));
+        for (dir, next_score) in membank.iter() {
+            if rnd_score >= *next_score {
+                rnd_score -= next_score;
+            } else {
+                info!("Moving: {}", dir);
+                self.move_it(dir, map, others);
+                break;
             }
         }
         membank.clear();
     }
 
-    fn op_eat(&mut self, map: &mut NystopiaMap) {
-        if !self.hungry() {
+    fn op_eat(&mut self, map: &NystopiaMap) {
+        if self.kind == NekobotKind::Carnivore || !self.hungry() {
+            return;
+        }
+        // Other bots may be after the same food, so it only gets eaten once everyone
+        // had their turn
+        if map.get_tile(self.row, self.col).is_some_and(|tile| tile.has_food()) {
+            self.claim = Some((self.row, self.col));
+        }
+    }
+
+    /// The tile of the food claimed this tick, if any, clearing the claim
+    pub fn take_claim(&mut self) -> Option<(u16, u16)> {
+        self.claim.take()
+    }
+
+    /// Gains `share` of the energy of a plant, as well as the bot digests it
+    pub fn digest(&mut self, map: &NystopiaMap, plant: usize, share: f32) {
+        let energy = map.get_plants()[plant].energy as f32 * self.digestion[plant] * share;
+        self.energy = self.energy.saturating_add(energy.round().min(u8::MAX as f32) as u8);
+    }
+
+    /// Loses energy, such as when fighting over food
+    pub fn spend(&mut self, energy: u8) {
+        self.energy = self.energy.saturating_sub(energy);
+    }
+
+    fn op_scavenge(&mut self, map: &mut NystopiaMap) {
+        if self.kind == NekobotKind::Carnivore || !self.hungry() {
             return;
         }
-        self.eat(map);
+        // Feed on a carcass here, but only as much as it takes to be full again
+        let bite = (self.hungry_threshold - self.energy) as u16;
+        let eaten = map.scavenge(self.row, self.col, bite);
+        self.energy = self.energy.saturating_add(eaten as u8);
     }
 
-    fn move_it(self: &mut Self, dir: &NekoDirs, map: &NystopiaMap) {
-        match dir {
-            NekoDirs::Here => {},
-            NekoDirs::Up => self.row = if self.row == 0 { 0 } else { self.row - 1 },
-            NekoDirs::Down => self.row = (map.get_rows() - 1).min(self.row + 1),
-            NekoDirs::Left => self.col = if self.col == 0 { 0 } else { self.col - 1 },
-            NekoDirs::Right => self.col = (map.get_cols() - 1).min(self.col + 1),
+    fn op_hunt(&mut self, map: &NystopiaMap, others: &mut Others) {
+        if self.kind != NekobotKind::Carnivore || !self.hungry() {
+            return;
+        }
+        // Catch the first herbivore found on this tile or a neighboring one
+        let mut reach = vec![(self.row, self.col)];
+        for dir in map.get_neighborhood().dirs() {
+            reach.extend(map.neighbor(self.row, self.col, dir));
+        }
+        let prey = others.iter_mut().find(|bot| {
+            bot.kind == NekobotKind::Herbivore && bot.is_alive() && reach.contains(&(bot.row, bot.col))
+        });
+        if let Some(prey) = prey {
+            info!("Bot #{} caught bot #{}", self.id, prey.id);
+            prey.caught();
+            self.energy = self.energy.saturating_add(self.prey_energy);
         }
     }
 
+    fn op_give(&mut self, map: &NystopiaMap, others: &mut Others, amount: u16) {
+        // Hand it to the neediest kin in reach; the bot keeps at least one energy for itself
+        let amount = (amount.min(u8::MAX as u16) as u8).min(self.energy.saturating_sub(1));
+        if amount == 0 {
+            return;
+        }
+        let mut reach = vec![(self.row, self.col)];
+        for dir in map.get_neighborhood().dirs() {
+            reach.extend(map.neighbor(self.row, self.col, dir));
+        }
+        let receiver = others
+            .iter_mut()
+            .filter(|bot| bot.is_alive() && reach.contains(&(bot.row, bot.col)) && self.is_kin(bot))
+            .min_by_key(|bot| bot.energy);
+        if let Some(receiver) = receiver {
+            // Some of it is lost on the way
+            let received = (amount as u16 * (100 - self.give_loss) as u16 / 100) as u8;
+            info!("Bot #{} gave {} energy to bot #{}", self.id, received, receiver.id);
+            self.energy -= amount;
+            receiver.energy = receiver.energy.saturating_add(received);
+        }
+    }
+
+    fn is_kin(&self, other: &Nekobot) -> bool {
+        match self.kin {
+            Kin::Off => true,
+            Kin::Species => self.species == other.species,
+            Kin::Lineage => self.lineage == other.lineage,
+        }
+    }
+
+    /// Eaten by a predator: nothing is left behind
+    fn caught(&mut self) {
+        self.energy = 0;
+        self.state = NekobotState::Dead;
+    }
+
+    fn move_it(&mut self, dir: &NekoDirs, map: &NystopiaMap, others: &mut Others) {
+        // Off the edge of a bounded map, into a wall or a bot in the way, the bot just
+        // stays where it is
+        if !self.can_move(map, others, dir) {
+            return;
+        }
+        let Some((row, col)) = map.neighbor(self.row, self.col, dir) else {
+            return;
+        };
+        if self.collision != Collision::Shared {
+            let (from_row, from_col) = (self.row, self.col);
+            let pushed_to = map.neighbor(row, col, dir);
+            if let Some(other) = others.occupant_mut(row, col) {
+                match (self.collision, pushed_to) {
+                    (Collision::Swap, _) => (other.row, other.col) = (from_row, from_col),
+                    (Collision::Push, Some(to)) => (other.row, other.col) = to,
+                    _ => return,
+                }
+                other.claim = None;
+            }
+        }
+        self.row = row;
+        self.col = col;
+        // Food claimed on the tile left behind is out of reach now
+        self.claim = None;
+    }
+
     fn hungry(&self) -> bool {
         self.get_energy() < self.hungry_threshold
     }
 
-    pub fn tick(self: &mut Self, map: &mut NystopiaMap) {
+    /// Runs the bot's program for a tick; `others` is the rest of the population
+    pub fn tick(&mut self, map: &mut NystopiaMap, others: &mut Others, rng: &mut SimRng) {
         if self.energy > 0 {
-            let mut membank: Vec<u64> = vec![];
-            for op in self.nekode.clone().iter() {
-                match op {
-                    NekoOps::Sense(dir) => self.op_sense(&mut membank, map, dir),
-                    NekoOps::Move => self.op_move(&mut membank, map),
-                    NekoOps::Eat => self.op_eat(map),
-                    NekoOps::Halt => break,
-                }
+            // Memories only matter to programs that recall them
+            if self.recalls() {
+                self.remember(map);
+            }
+            for _ in 0..self.speed {
+                self.run(map, others, rng);
             }
             self.energy -= 1;
+            self.age += 1;
             if self.hungry() {
                 self.state = NekobotState::Forage;
             } else {
                 self.state = NekobotState::Wander;
             }
-        } else {
+        } else if self.is_alive() {
+            // The body stays behind as a carcass
             self.state = NekobotState::Dead;
+            map.drop_carcass(self.row, self.col);
+        } else {
+            self.dead_ticks = self.dead_ticks.saturating_add(1);
         }
     }
 
-    pub fn tick_old(self: &mut Self, map: &mut NystopiaMap) {
+    /// Puts the bot where and in the state a recording says it is
+    pub(crate) fn set_position(&mut self, row: u16, col: u16) {
+        self.row = row;
+        self.col = col;
+    }
+
+    pub(crate) fn set_state(&mut self, state: NekobotState) {
+        self.state = state;
+    }
+
+    pub fn get_dead_ticks(&self) -> u16 {
+        self.dead_ticks
+    }
+
+    pub fn get_age(&self) -> u64 {
+        self.age
+    }
+
+    fn run(&mut self, map: &mut NystopiaMap, others: &mut Others, rng: &mut SimRng) {
+        let mut membank: Vec<(NekoDirs, u64)> = vec![];
+        for op in self.nekode.clone().iter() {
+            match op {
+                NekoOps::Sense(dir) => self.op_sense(&mut membank, map, others, dir, rng),
+                NekoOps::SenseScent(dir) => self.op_sense_scent(&mut membank, map, others, dir),
+                NekoOps::Recall(dir) => self.op_recall(&mut membank, map, others, dir),
+                NekoOps::Move => self.op_move(&mut membank, map, others, rng),
+                NekoOps::Eat => self.op_eat(map),
+                NekoOps::Scavenge => self.op_scavenge(map),
+                NekoOps::Hunt => self.op_hunt(map, others),
+                NekoOps::Deposit(amount) => map.deposit_scent(self.row, self.col, *amount as f32),
+                NekoOps::Emit(channel, value) => map.emit(Signal {
+                    row: self.row,
+                    col: self.col,
+                    channel: *channel,
+                    value: *value,
+                    sender: self.id,
+                }),
+                NekoOps::Listen(channel) => self.op_listen(&mut membank, map, others, *channel),
+                NekoOps::Give(amount) => self.op_give(map, others, *amount),
+                NekoOps::Halt => break,
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn tick_old(&mut self, map: &mut NystopiaMap, others: &mut Others, rng: &mut SimRng) {
         if self.energy > 0 {
-            let mut rng = nanorand::tls_rng();
-            let dirs = [
-                NekoDirs::Up,
-                NekoDirs::Down,
-                NekoDirs::Left,
-                NekoDirs::Right,
-            ];
+            let dirs = map.get_neighborhood().dirs();
             self.state = NekobotState::Wander;
-            if !self.hungry() || (!self.eat(map) && !self.forage(map)) {
-                self.move_it(&dirs[(rng.generate::<u8>() % 4) as usize], map);
+            if !self.hungry() || (!self.eat(map) && !self.forage(map, others, rng)) {
+                self.move_it(&dirs[rng.generate::<usize>() % dirs.len()], map, others);
             }
             self.energy -= 1;
         } else {
@@ -181,208 +669,248 @@ impl Nekobot {
         }
     }
 
-    fn get_energy(self: &Self) -> u8 {
+    pub fn get_energy(&self) -> u8 {
         self.energy
     }
 
-    fn eat(self: &mut Self, map: &mut NystopiaMap) -> bool {
-        if map.consume(self.row, self.col) {
-            self.energy += 20;
+    fn eat(&mut self, map: &mut NystopiaMap) -> bool {
+        if let Some(plant) = map.consume(self.row, self.col) {
+            self.digest(map, plant, 1.0);
             true
         } else {
             false
         }
     }
 
-    fn sight_dims(self: &Self, map: &NystopiaMap) -> (u16, u16, u16, u16) {
-        let left = if self.col > self.sight {
-            self.col - self.sight
-        } else {
-            0
-        };
-        let right = map.get_cols().min(self.col + self.sight);
-        let top = if self.row > self.sight {
-            self.row - self.sight
-        } else {
-            0
-        };
-        let bottom = map.get_rows().min(self.row + self.sight);
-
-        (left, right, top, bottom)
-    }
+    /// All the tiles within sight that aren't hidden behind something that blocks the view
+    pub fn visible_tiles(&self, map: &NystopiaMap) -> Vec<(u16, u16)> {
+        let sight = self.sight as i32;
+        let here = (self.row, self.col);
+        let mut tiles = vec![];
+
+        for drow in -sight..=sight {
+            for dcol in -sight..=sight {
+                let Some(there) = map.wrap(self.row as i32 + drow, self.col as i32 + dcol) else {
+                    continue;
+                };
+                if map.distance(here, there) <= self.sight as f64 && map.line_of_sight(here, there) {
+                    tiles.push(there);
+                }
+            }
+        }
 
-    fn compute_food_move_score(&self, food_row: u64, food_col: u64) -> u64 {
-        let side_a = (self.row as f64) - (food_row as f64);
-        let side_b = (self.col as f64) - (food_col as f64);
-        let side_c = side_a.abs().hypot(side_b.abs()).min(self.sight as f64);
-        let score = (self.see_food_move_score as f64)*(self.sight as f64 - side_c)/(self.sight as f64);
-        score.round() as u64 + self.move_score
+        tiles
     }
 
-    fn forage2(self: &Self, map: &NystopiaMap, dir: &NekoDirs) -> u64 {
-        // If there's food here, then grant it the max score
-        if let NekoDirs::Here = dir {
-            if let Some(tile) = map.get_tile(self.row, self.col) {
-                if tile.has_food() {
-                    return self.see_food_move_score;
-                } else {
-                    return self.move_score;
+    /// Looks over the visible tiles for the nearest uneaten food, returning its (row, col)
+    /// location. Plants that digest poorly count as being further away.
+    fn nearest_food(&self, map: &NystopiaMap, rng: &mut SimRng) -> Option<(u16, u16)> {
+        let mut nearest: Option<(u16, u16, f64)> = None;
+
+        for (row, col) in self.visible_tiles(map) {
+            let Some(tile) = map.get_tile(row, col) else {
+                continue;
+            };
+            let Some(plant) = tile.get_plant() else {
+                continue;
+            };
+            if tile.eaten() {
+                continue;
+            }
+            let dist = map.distance((self.row, self.col), (row, col));
+
+            let weighted = dist / self.digestion[plant].max(0.01) as f64;
+            let closer = match nearest {
+                None => true,
+                Some((_, _, nearest_dist)) => {
+                    weighted < nearest_dist
+                        || (weighted == nearest_dist && (rng.generate::<u8>() % 2) == 1)
                 }
+            };
+            if closer {
+                nearest = Some((row, col, weighted));
             }
         }
 
-        let (left, right, top, bottom) = self.sight_dims(map);
-
-        let mut nearest_row = 0;
-        let mut nearest_col = 0;
-        let mut nearest_dist = self.sight * 2;
-
-        for row in top..bottom {
-            for col in left..right {
-                if let Some(tile) = map.get_tile(row, col) {
-                    let aside = row.max(self.row) - row.min(self.row);
-                    let bside = col.max(self.col) - col.min(self.col);
-                    let cside = (((aside * aside) + (bside * bside)) as f64).sqrt() as u16;
-
-                    if cside <= self.sight && tile.has_food() && !tile.eaten() {
-                        if (cside < nearest_dist)
-                            || ((cside == nearest_dist)
-                                && (nanorand::tls_rng().generate::<u8>() % 2) == 1)
-                        {
-                            nearest_dist = cside;
-                            nearest_row = row;
-                            nearest_col = col;
-                        }
-                    }
-                }
-            }
+        nearest.map(|(row, col, _)| (row, col))
+    }
+
+    /// Looks over the visible tiles for the nearest living herbivore, returning its
+    /// (row, col) location
+    fn nearest_prey(&self, map: &NystopiaMap, others: &Others) -> Option<(u16, u16)> {
+        let here = (self.row, self.col);
+        others
+            .iter()
+            .filter(|bot| bot.kind == NekobotKind::Herbivore && bot.is_alive())
+            .map(|bot| (bot.row, bot.col))
+            .filter(|&there| map.distance(here, there) <= self.sight as f64 && map.line_of_sight(here, there))
+            .min_by(|&a, &b| map.distance(here, a).total_cmp(&map.distance(here, b)))
+    }
+
+    /// Whatever this kind of bot feeds on: plants for herbivores, herbivores for carnivores
+    fn nearest_target(&self, map: &NystopiaMap, others: &Others, rng: &mut SimRng) -> Option<(u16, u16)> {
+        match self.kind {
+            NekobotKind::Herbivore => self.nearest_food(map, rng),
+            NekobotKind::Carnivore => self.nearest_prey(map, others),
         }
+    }
 
-        if nearest_dist <= self.sight {
-            if nearest_row < self.row {
-                if nearest_col < self.col {
-                    if self.col - nearest_col < self.row - nearest_row {
-                        if let NekoDirs::Up = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    } else {
-                        if let NekoDirs::Left = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    }
-                } else {
-                    if nearest_col - self.col < self.row - nearest_row {
-                        if let NekoDirs::Up = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    } else {
-                        if let NekoDirs::Right = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    }
-                }
-            } else {
-                if nearest_col < self.col {
-                    if self.col - nearest_col < nearest_row - self.row {
-                        if let NekoDirs::Down = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    } else {
-                        if let NekoDirs::Left = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    }
-                } else {
-                    if nearest_col - self.col < nearest_row - self.row {
-                        if let NekoDirs::Down = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    } else {
-                        if let NekoDirs::Right = dir {
-                            return self.compute_food_move_score(nearest_row as u64, nearest_col as u64);
-                        }
-                    }
+    /// Picks the neighboring direction that brings the bot closest to a tile
+    fn food_dir(&self, map: &NystopiaMap, food: (u16, u16)) -> NekoDirs {
+        let mut best = NekoDirs::Here;
+        let mut best_dist = map.distance((self.row, self.col), food);
+        for dir in map.get_neighborhood().dirs() {
+            if let Some(next) = map.neighbor(self.row, self.col, dir).filter(|&(r, c)| map.passable(r, c)) {
+                let dist = map.distance(next, food);
+                if dist < best_dist {
+                    best = dir.clone();
+                    best_dist = dist;
                 }
             }
         }
-        self.move_score
+        best
     }
 
-    fn forage(self: &mut Self, map: &NystopiaMap) -> bool {
-        let (left, right, top, bottom) = self.sight_dims(map);
-
-        let mut nearest_row = 0;
-        let mut nearest_col = 0;
-        let mut nearest_dist = self.sight * 2;
-
-        self.state = NekobotState::Forage;
+    fn compute_food_move_score(&self, map: &NystopiaMap, food_row: u16, food_col: u16) -> u64 {
+        let side_c = map
+            .distance((self.row, self.col), (food_row, food_col))
+            .min(self.sight as f64);
+        let score = (self.see_food_move_score as f64)*(self.sight as f64 - side_c)/(self.sight as f64);
+        score.round() as u64 + self.move_score
+    }
 
-        for row in top..bottom {
-            for col in left..right {
-                if let Some(tile) = map.get_tile(row, col) {
-                    let aside = row.max(self.row) - row.min(self.row);
-                    let bside = col.max(self.col) - col.min(self.col);
-                    let cside = (((aside * aside) + (bside * bside)) as f64).sqrt() as u16;
-
-                    if cside <= self.sight && tile.has_food() && !tile.eaten() {
-                        if (cside < nearest_dist)
-                            || ((cside == nearest_dist)
-                                && (nanorand::tls_rng().generate::<u8>() % 2) == 1)
-                        {
-                            nearest_dist = cside;
-                            nearest_row = row;
-                            nearest_col = col;
-                        }
-                    }
-                }
+    fn forage2(&self, map: &NystopiaMap, others: &Others, dir: &NekoDirs, rng: &mut SimRng) -> u64 {
+        // If there's food here, then grant it the max score
+        if let NekoDirs::Here = dir {
+            let food_here = match self.kind {
+                NekobotKind::Herbivore => map.get_tile(self.row, self.col).is_some_and(|tile| tile.has_food()),
+                NekobotKind::Carnivore => self.nearest_prey(map, others) == Some((self.row, self.col)),
+            };
+            if food_here {
+                return self.see_food_move_score;
+            } else {
+                return self.move_score;
             }
         }
 
-        if nearest_dist <= self.sight {
-            if nearest_row < self.row {
-                if nearest_col < self.col {
-                    if self.col - nearest_col < self.row - nearest_row {
-                        self.move_it(&NekoDirs::Up, map);
-                    } else {
-                        self.move_it(&NekoDirs::Left, map);
-                    }
-                } else {
-                    if nearest_col - self.col < self.row - nearest_row {
-                        self.move_it(&NekoDirs::Up, map);
-                    } else {
-                        self.move_it(&NekoDirs::Right, map);
-                    }
-                }
-            } else {
-                if nearest_col < self.col {
-                    if self.col - nearest_col < nearest_row - self.row {
-                        self.move_it(&NekoDirs::Down, map);
-                    } else {
-                        self.move_it(&NekoDirs::Left, map);
-                    }
-                } else {
-                    if nearest_col - self.col < nearest_row - self.row {
-                        self.move_it(&NekoDirs::Down, map);
-                    } else {
-                        self.move_it(&NekoDirs::Right, map);
-                    }
-                }
+        if let Some((food_row, food_col)) = self.nearest_target(map, others, rng) {
+            if self.food_dir(map, (food_row, food_col)) == *dir {
+                return self.compute_food_move_score(map, food_row, food_col);
             }
+        }
+        self.move_score
+    }
+
+    #[allow(dead_code)]
+    fn forage(&mut self, map: &NystopiaMap, others: &mut Others, rng: &mut SimRng) -> bool {
+        self.state = NekobotState::Forage;
+
+        if let Some(food) = self.nearest_food(map, rng) {
+            self.move_it(&self.food_dir(map, food), map, others);
             true
         } else {
             false
         }
     }
 
-    pub fn get_row(self: &Self) -> u16 {
+    pub fn get_row(&self) -> u16 {
         self.row
     }
 
-    pub fn get_col(self: &Self) -> u16 {
+    pub fn get_col(&self) -> u16 {
         self.col
     }
 
-    pub fn get_label(self: &Self) -> &String {
-        &(self.label)
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_glyph(&self) -> char {
+        self.glyph
+    }
+
+    pub fn get_lineage(&self) -> u32 {
+        self.lineage
+    }
+
+    /// Number of food tiles the bot currently remembers
+    pub fn get_memories(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Whether the bot keeps a memory of the food it sees, which only bots with room for
+    /// memories and a program that recalls them do
+    pub fn recalls(&self) -> bool {
+        self.memory_size > 0 && self.nekode.iter().any(|op| matches!(op, NekoOps::Recall(_)))
+    }
+}
+
+/// The rest of the population, as seen from the bot being ticked
+pub struct Others<'a> {
+    before: &'a mut [Nekobot],
+    after: &'a mut [Nekobot],
+}
+
+impl<'a> Others<'a> {
+    /// Splits the bot at `index` off from everyone else
+    pub fn split(bots: &'a mut [Nekobot], index: usize) -> (&'a mut Nekobot, Others<'a>) {
+        let (before, rest) = bots.split_at_mut(index);
+        let (bot, after) = rest.split_first_mut().expect("bot index out of range");
+        (bot, Others { before, after })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Nekobot> {
+        self.before.iter().chain(self.after.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Nekobot> {
+        self.before.iter_mut().chain(self.after.iter_mut())
+    }
+
+    /// Whether a living bot stands on (row, col)
+    pub fn occupied(&self, row: u16, col: u16) -> bool {
+        self.iter().any(|bot| bot.is_alive() && bot.row == row && bot.col == col)
+    }
+
+    /// The living bot standing on (row, col), if any
+    pub fn occupant_mut(&mut self, row: u16, col: u16) -> Option<&mut Nekobot> {
+        self.iter_mut().find(|bot| bot.is_alive() && bot.row == row && bot.col == col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+
+    #[test]
+    fn mutants_digest_a_little_differently() {
+        let config: SimConfig = toml::from_str("[[plant]]\nname = \"a\"\n[[plant]]\nname = \"b\"\n[[plant]]\nname = \"c\"\n").unwrap();
+        let settings = Settings::default();
+        let mut rng = SimRng::new(1);
+        let map = NystopiaMap::new(&settings, 10, 10, &config, &mut rng).unwrap();
+        let species = BotSpecies::from_settings(&settings).remove(0);
+        let parent = Nekobot::new_rand(0, 0, &species, &map, &[], &settings, &mut rng);
+
+        let mutant = parent.mutant(1, &species, &map, &[], &settings, &mut rng);
+        assert!(mutant.digestion != parent.digestion);
+        for (a, b) in mutant.digestion.iter().zip(parent.digestion.iter()) {
+            assert!((a / b - 1.0).abs() < 0.25);
+        }
+        let mean = mutant.digestion.iter().sum::<f32>() / mutant.digestion.len() as f32;
+        assert!((mean - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mutants_get_a_glyph_of_their_own() {
+        let settings = Settings::default();
+        let mut rng = SimRng::new(1);
+        let map = NystopiaMap::new(&settings, 10, 10, &SimConfig::default(), &mut rng).unwrap();
+        let species = BotSpecies::from_settings(&settings).remove(0);
+        let parent = Nekobot::new_rand(0, 0, &species, &map, &[], &settings, &mut rng);
+
+        let mutant = parent.mutant(7, &species, &map, &[], &settings, &mut rng);
+        assert_eq!(mutant.glyph, species.glyph(7));
+        assert!(mutant.glyph != parent.glyph);
     }
 }
@@ -0,0 +1,35 @@
+use super::disaster::Disaster;
+use super::immigration::Immigration;
+use super::plant::PlantSpecies;
+use super::species::BotSpecies;
+use serde::Deserialize;
+use std::fs;
+
+/// Simulation settings loaded from a TOML file, for everything that doesn't fit well on
+/// the command line
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimConfig {
+    /// Vegetation species, as `[[plant]]` tables
+    #[serde(rename = "plant")]
+    pub plants: Vec<PlantSpecies>,
+
+    /// Disasters striking at given ticks, as `[[disaster]]` tables
+    #[serde(rename = "disaster")]
+    pub disasters: Vec<Disaster>,
+
+    /// Bot species, as `[[species]]` tables; these replace the bots set up on the
+    /// command line
+    pub species: Vec<BotSpecies>,
+
+    /// Bots arriving at given ticks, as `[[immigration]]` tables
+    #[serde(rename = "immigration")]
+    pub immigrations: Vec<Immigration>,
+}
+
+impl SimConfig {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,124 @@
+use super::bot::Nekobot;
+use super::map::NystopiaMap;
+use super::rng::SimRng;
+use log::info;
+use nanorand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How bots trying to eat the same food in one tick settle who gets it
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ContestRule {
+    /// One of them, picked at random, eats it all
+    Random,
+    /// The one with the most energy eats it all
+    Strongest,
+    /// They all get an equal share
+    Split,
+    /// They all pay an energy cost to fight over it, then the strongest eats it all
+    Fight,
+}
+
+/// Settles who gets the food claimed this tick, once every bot has had its turn, so the
+/// order bots are ticked in doesn't matter
+pub fn resolve(bots: &mut [Nekobot], map: &mut NystopiaMap, rule: ContestRule, fight_cost: u8, rng: &mut SimRng) {
+    // Claimants by tile, in a stable order; bots caught since they made their claim don't
+    // get to eat
+    let mut claims: BTreeMap<(u16, u16), Vec<usize>> = BTreeMap::new();
+    for (i, bot) in bots.iter_mut().enumerate() {
+        if let Some(tile) = bot.take_claim().filter(|_| bot.is_alive()) {
+            claims.entry(tile).or_default().push(i);
+        }
+    }
+
+    for ((row, col), claimants) in claims {
+        let Some(plant) = map.consume(row, col) else {
+            continue;
+        };
+        if claimants.len() == 1 {
+            bots[claimants[0]].digest(map, plant, 1.0);
+            continue;
+        }
+
+        info!(
+            "Contest at ({}, {}) between bots {}",
+            row,
+            col,
+            claimants.iter().map(|&i| format!("#{}", bots[i].get_id())).collect::<Vec<String>>().join(",")
+        );
+        match rule {
+            ContestRule::Random => {
+                let winner = claimants[rng.generate::<usize>() % claimants.len()];
+                bots[winner].digest(map, plant, 1.0);
+            }
+            ContestRule::Strongest => {
+                let winner = strongest(bots, &claimants, rng);
+                bots[winner].digest(map, plant, 1.0);
+            }
+            ContestRule::Split => {
+                let share = 1.0 / claimants.len() as f32;
+                for &i in claimants.iter() {
+                    bots[i].digest(map, plant, share);
+                }
+            }
+            ContestRule::Fight => {
+                for &i in claimants.iter() {
+                    bots[i].spend(fight_cost);
+                }
+                let winner = strongest(bots, &claimants, rng);
+                bots[winner].digest(map, plant, 1.0);
+            }
+        }
+    }
+}
+
+/// The claimant with the most energy, ties broken at random
+fn strongest(bots: &[Nekobot], claimants: &[usize], rng: &mut SimRng) -> usize {
+    let most = claimants.iter().map(|&i| bots[i].get_energy()).max().unwrap_or(0);
+    let tied: Vec<usize> = claimants.iter().copied().filter(|&i| bots[i].get_energy() == most).collect();
+    tied[rng.generate::<usize>() % tied.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{NekobotState, Others};
+    use crate::config::SimConfig;
+    use crate::settings::Settings;
+    use crate::species::BotSpecies;
+
+    #[test]
+    fn caught_bots_get_no_food() {
+        // A single food tile, and two hungry bots standing on it that only know how to eat
+        let settings = Settings {
+            food_prob: 100,
+            program: Some("Eat".parse().unwrap()),
+            ..Settings::default()
+        };
+        let mut rng = SimRng::new(1);
+        let mut map = NystopiaMap::new(&settings, 1, 1, &SimConfig::default(), &mut rng).unwrap();
+        let mut species = BotSpecies::from_settings(&settings).remove(0);
+        species.hunger = Some((u8::MAX, u8::MAX));
+        let mut bots = vec![];
+        for id in 0..2 {
+            let bot = Nekobot::new_rand(id, 0, &species, &map, &bots, &settings, &mut rng);
+            bots.push(bot);
+        }
+
+        for i in 0..bots.len() {
+            let (bot, mut others) = Others::split(&mut bots, i);
+            bot.tick(&mut map, &mut others, &mut rng);
+        }
+        // The first one is caught before the contest is settled
+        bots[0].set_state(NekobotState::Dead);
+        let energy: Vec<u8> = bots.iter().map(|bot| bot.get_energy()).collect();
+
+        resolve(&mut bots, &mut map, ContestRule::Split, 0, &mut rng);
+        assert!(!bots[0].is_alive());
+        assert_eq!(bots[0].get_energy(), energy[0]);
+        assert!(bots[1].get_energy() > energy[1]);
+        assert!(map.get_tile(0, 0).unwrap().eaten());
+    }
+}
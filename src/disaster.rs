@@ -0,0 +1,96 @@
+use super::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisasterKind {
+    /// Plants in the area stop growing back
+    Drought,
+    /// Plants in the area burn down, and the fire spreads to neighboring plants
+    Fire,
+    /// The area can't be walked on
+    Flood,
+}
+
+impl fmt::Display for DisasterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Drought => write!(f, "Drought"),
+            Self::Fire => write!(f, "Fire"),
+            Self::Flood => write!(f, "Flood"),
+        }
+    }
+}
+
+/// A disaster striking a circular area of the map, either scheduled in the config file
+/// as a `[[disaster]]` table or happening at random
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Disaster {
+    pub kind: DisasterKind,
+
+    /// Tick it strikes at (only used when scheduled)
+    #[serde(default)]
+    pub tick: u64,
+
+    /// Center of the area, random if not given
+    #[serde(default)]
+    pub row: Option<u16>,
+    #[serde(default)]
+    pub col: Option<u16>,
+
+    pub radius: u16,
+
+    /// How long a drought or flood lasts, in ticks (fires last as long as they find
+    /// something to burn)
+    #[serde(default)]
+    pub duration: u16,
+}
+
+/// Decides which disasters strike on each tick
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DisasterSchedule {
+    scheduled: Vec<Disaster>,
+    // Chance per tick of each kind of disaster striking, in tenths of a percent
+    rates: [(DisasterKind, u16); 3],
+    radius: u16,
+    duration: u16,
+}
+
+impl DisasterSchedule {
+    pub fn new(settings: &Settings, mut scheduled: Vec<Disaster>) -> Self {
+        scheduled.sort_by_key(|d| d.tick);
+        Self {
+            scheduled,
+            rates: [
+                (DisasterKind::Drought, settings.drought_rate),
+                (DisasterKind::Fire, settings.fire_rate),
+                (DisasterKind::Flood, settings.flood_rate),
+            ],
+            radius: settings.disaster_radius,
+            duration: settings.disaster_duration,
+        }
+    }
+
+    /// Disasters striking at `tick`: the ones scheduled for it, plus any random ones
+    /// picked by the rolls (one roll per kind of disaster, from 0 to 999)
+    pub fn due(&mut self, tick: u64, rolls: [u16; 3]) -> Vec<Disaster> {
+        let count = self.scheduled.iter().take_while(|d| d.tick <= tick).count();
+        let mut due: Vec<Disaster> = self.scheduled.drain(..count).collect();
+
+        for ((kind, rate), roll) in self.rates.iter().zip(rolls) {
+            if roll < *rate {
+                due.push(Disaster {
+                    kind: *kind,
+                    tick,
+                    row: None,
+                    col: None,
+                    radius: self.radius,
+                    duration: self.duration,
+                });
+            }
+        }
+        due
+    }
+}
@@ -0,0 +1,154 @@
+use super::bot::Nekobot;
+use super::map::NystopiaMap;
+use super::nekode::Nekode;
+use super::rng::SimRng;
+use super::species::BotSpecies;
+use super::settings::Settings;
+use log::info;
+use nanorand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Where newly arriving bots show up
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ArrivalSite {
+    /// Anywhere on the map
+    Random,
+    /// Along the edges of the map
+    Edge,
+}
+
+/// Bots arriving at a given tick, as an `[[immigration]]` table
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Immigration {
+    pub tick: u64,
+
+    #[serde(default = "Immigration::default_count")]
+    pub count: u32,
+
+    /// Name of the species they belong to (defaults to the first one)
+    #[serde(default)]
+    pub species: Option<String>,
+
+    /// Program they run instead of their species' own
+    #[serde(default)]
+    pub program: Option<Nekode>,
+
+    /// Where they show up (defaults to `--arrive-at`)
+    #[serde(default)]
+    pub at: Option<ArrivalSite>,
+}
+
+impl Immigration {
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+/// Decides which bots join the population on each tick
+#[derive(Serialize, Deserialize)]
+pub struct ImmigrationSchedule {
+    // Scheduled arrivals still to come, along with the index of their species, in the
+    // order they're due
+    scheduled: Vec<(Immigration, usize)>,
+    // Chance per tick of a bot arriving, in tenths of a percent
+    rate: u16,
+    at: ArrivalSite,
+    program: Option<Nekode>,
+}
+
+impl ImmigrationSchedule {
+    pub fn new(
+        settings: &Settings,
+        scheduled: Vec<Immigration>,
+        species: &[BotSpecies],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut resolved = vec![];
+        for immigration in scheduled {
+            let species_id = match &immigration.species {
+                Some(name) => species
+                    .iter()
+                    .position(|s| s.name == *name)
+                    .ok_or_else(|| format!("unknown species `{}` in [[immigration]]", name))?,
+                None => 0,
+            };
+            resolved.push((immigration, species_id));
+        }
+        resolved.sort_by_key(|(immigration, _)| immigration.tick);
+
+        Ok(Self {
+            scheduled: resolved,
+            rate: settings.immigration_rate,
+            at: settings.arrive_at,
+            program: settings.immigrant_program.clone(),
+        })
+    }
+
+    /// Lets in the bots arriving on the map's current tick: the scheduled ones that are
+    /// due (including any scheduled before the first tick ran), plus one of a random species
+    /// now and then. They are numbered from `next_id` on.
+    pub fn arrive(
+        &mut self,
+        bots: &mut Vec<Nekobot>,
+        next_id: &mut u32,
+        species: &[BotSpecies],
+        map: &NystopiaMap,
+        settings: &Settings,
+        rng: &mut SimRng,
+    ) {
+        let tick = map.get_tick();
+        let count = self.scheduled.iter().take_while(|(immigration, _)| immigration.tick <= tick).count();
+        for (immigration, species_id) in self.scheduled.drain(..count) {
+            let arrival = Immigration {
+                program: immigration.program.clone().or_else(|| self.program.clone()),
+                at: immigration.at.or(Some(self.at)),
+                ..immigration.clone()
+            };
+            Self::admit(bots, next_id, species, &(arrival, species_id), map, settings, rng);
+        }
+
+        if !species.is_empty() && rng.generate::<u16>() % 1000 < self.rate {
+            let species_id = rng.generate::<usize>() % species.len();
+            let arrival = Immigration {
+                tick,
+                count: 1,
+                species: None,
+                program: self.program.clone(),
+                at: Some(self.at),
+            };
+            Self::admit(bots, next_id, species, &(arrival, species_id), map, settings, rng);
+        }
+    }
+
+    fn admit(
+        bots: &mut Vec<Nekobot>,
+        next_id: &mut u32,
+        species: &[BotSpecies],
+        (arrival, species_id): &(Immigration, usize),
+        map: &NystopiaMap,
+        settings: &Settings,
+        rng: &mut SimRng,
+    ) {
+        let species = BotSpecies {
+            program: arrival.program.clone().or_else(|| species[*species_id].program.clone()),
+            ..species[*species_id].clone()
+        };
+        for _ in 0..arrival.count {
+            let mut newcomer = Nekobot::new_rand(*next_id, *species_id, &species, map, bots, settings, rng);
+            *next_id += 1;
+            if arrival.at == Some(ArrivalSite::Edge) {
+                newcomer.move_to_edge(map, bots, settings, rng);
+            }
+            info!(
+                "Bot #{} of {} arrived at ({}, {})",
+                newcomer.get_id(),
+                species.name,
+                newcomer.get_row(),
+                newcomer.get_col()
+            );
+            bots.push(newcomer);
+        }
+    }
+}
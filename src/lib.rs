@@ -0,0 +1,21 @@
+//! The nekobots simulation, without any of the terminal around it. A `World` is set up
+//! through `World::builder()` and run one tick at a time with `World::step()`.
+
+pub mod bot;
+pub mod config;
+pub mod contest;
+pub mod disaster;
+pub mod immigration;
+pub mod lifecycle;
+pub mod map;
+pub mod nekode;
+pub mod plant;
+pub mod recording;
+pub mod renderer;
+pub mod rng;
+pub mod settings;
+pub mod species;
+pub mod world;
+
+pub use settings::Settings;
+pub use world::{SnapshotFormat, World, WorldBuilder};
@@ -0,0 +1,72 @@
+use super::bot::Nekobot;
+use super::map::NystopiaMap;
+use super::rng::SimRng;
+use super::species::BotSpecies;
+use super::settings::Settings;
+use log::info;
+use nanorand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// What becomes of a bot once it has been dead for a while
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum DeadPolicy {
+    /// It stays in the population for good
+    Leave,
+    /// It's taken out of the population
+    Remove,
+    /// It's replaced by a fresh random bot of the same species
+    Respawn,
+    /// It's replaced by a mutated copy of a random survivor
+    Mutate,
+}
+
+/// What to do when every bot has died
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CrashPolicy {
+    /// Say so on screen and keep going
+    Report,
+    /// Say so on screen and pause until resumed
+    Pause,
+}
+
+/// Deals with the bots that have been dead for `--dead-time` ticks, as the dead policy says.
+/// Newborns are numbered from `next_id` on.
+pub fn reap(
+    bots: &mut Vec<Nekobot>,
+    next_id: &mut u32,
+    species: &[BotSpecies],
+    map: &NystopiaMap,
+    settings: &Settings,
+    rng: &mut SimRng,
+) {
+    let gone = |bot: &Nekobot| !bot.is_alive() && bot.get_dead_ticks() >= settings.dead_time;
+
+    match settings.dead {
+        DeadPolicy::Leave => {}
+        DeadPolicy::Remove => bots.retain(|bot| !gone(bot)),
+        DeadPolicy::Respawn | DeadPolicy::Mutate => {
+            for i in 0..bots.len() {
+                if !gone(&bots[i]) {
+                    continue;
+                }
+                let survivors: Vec<usize> = (0..bots.len()).filter(|&j| bots[j].is_alive()).collect();
+                let id = *next_id;
+                *next_id += 1;
+                let newborn = if settings.dead == DeadPolicy::Mutate && !survivors.is_empty() {
+                    let parent = &bots[survivors[rng.generate::<usize>() % survivors.len()]];
+                    info!("Bot #{} replaced by bot #{}, a mutant of bot #{}", bots[i].get_id(), id, parent.get_id());
+                    parent.mutant(id, &species[parent.get_species()], map, bots, settings, rng)
+                } else {
+                    // With nobody left to copy, start over from scratch
+                    let species_id = bots[i].get_species();
+                    info!("Bot #{} replaced by bot #{}", bots[i].get_id(), id);
+                    Nekobot::new_rand(id, species_id, &species[species_id], map, bots, settings, rng)
+                };
+                bots[i] = newborn;
+            }
+        }
+    }
+}
@@ -1,112 +1,499 @@
-mod bot;
-mod map;
-mod renderer;
 mod terminal;
 
 use simplelog::{Config, LevelFilter, WriteLogger};
-use bot::Nekobot;
 use clap::Parser;
-use crossterm::event::{poll, read, Event, KeyCode};
-use map::NystopiaMap;
-use renderer::Renderer;
+use log::info;
+use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use nekobots::bot::Nekobot;
+use nekobots::config::SimConfig;
+use nekobots::lifecycle::CrashPolicy;
+use nekobots::map::{Neighborhood, NystopiaMap};
+use nekobots::recording::{Recorder, Recording, Replay};
+use nekobots::renderer::Renderer;
+use nekobots::species::BotSpecies;
+use nekobots::{Settings, World};
+use terminal::GlyphBy;
 use std::io::{stdout, Write};
 use std::time::{Duration, Instant};
-use log::info;
 use std::fs::File;
 
+/// Time the simulation runs for between draws at full speed, in msec
+const FRAME_MSEC: u128 = 50;
+
+/// Longest time between ticks that slowing down goes to, in msec
+const MAX_PERIOD: u128 = 10000;
+
+/// Number of ticks a replay jumps back or ahead by at a time
+const SEEK_TICKS: u64 = 100;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct ProgArgs {
-    /// Number of bots to create
-    #[arg(short, long, default_value_t = 10, value_name = "BOTS")]
-    bots: u8,
+    #[command(flatten)]
+    settings: Settings,
 
-    /// Tick delay in msec (inverse of speed)
+    /// Tick delay in msec (inverse of speed), which can be changed while running
     #[arg(short, long, default_value_t = 250u128, value_name = "MSEC")]
     tick_delay: u128,
 
-    /// Sight (how many squares ahead a bot can "see")
-    #[arg(short, long, default_value_t = 10, value_name = "SQUARES")]
-    sight: u16,
+    /// World width in tiles (defaults to the width of the terminal)
+    #[arg(long, value_name = "TILES")]
+    width: Option<u16>,
+
+    /// World height in tiles (defaults to the height of the terminal)
+    #[arg(long, value_name = "TILES")]
+    height: Option<u16>,
+
+    /// What to do when every bot has died
+    #[arg(long, value_enum, default_value_t = CrashPolicy::Report, value_name = "POLICY")]
+    on_crash: CrashPolicy,
+
+    /// What the glyph a bot is drawn with tells about it
+    #[arg(long, value_enum, default_value_t = GlyphBy::Species, value_name = "GLYPHS")]
+    glyph_by: GlyphBy,
 
-    /// Vegetation regrowth time (in ticks)
-    #[arg(short, long, default_value_t = 100, value_name = "TICKS")]
-    regrow_time: u16,
+    /// Run without the terminal, as fast as possible, and print a summary at the end
+    #[arg(long)]
+    headless: bool,
 
-    /// Map vegetation probability (in percent)
-    #[arg(short, long, default_value_t = 5, value_name = "PERCENT")]
-    food_prob: u8,
+    /// Tick to run until with `--headless`, counting from the start of the run even after
+    /// `--load` (stops early if every bot dies)
+    #[arg(long, default_value_t = 1000, value_name = "TICKS")]
+    ticks: u64,
+
+    /// Seed for the random number generator; a run can be reproduced exactly by giving
+    /// it the same seed and options again (random if not given)
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// Resume a world saved with `--save`, instead of setting up a new one; the world keeps
+    /// the settings and size it was saved with
+    #[arg(long, value_name = "FILE")]
+    load: Option<String>,
+
+    /// Save the whole world to a file on exit, and whenever `s` is pressed (as JSON if the
+    /// name ends in .json, in a compact binary format otherwise)
+    #[arg(long, value_name = "FILE")]
+    save: Option<String>,
+
+    /// Record the run to a file as it goes: the world at the start, then what changed on
+    /// every tick
+    #[arg(long, value_name = "FILE")]
+    record: Option<String>,
+
+    /// Play back a run recorded with `--record`, instead of simulating one
+    #[arg(long, value_name = "FILE", conflicts_with = "headless")]
+    replay: Option<String>,
+
+    /// Optionally load plant species and other settings from a TOML file
+    #[arg(short, long, value_name = "CONFIGFILE")]
+    config: Option<String>,
 
     /// Optionally log activity to a file
     #[arg(short, long, value_name = "LOGFILENAME")]
     log: Option<String>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Draws the whole visible part of the world from scratch
+fn redraw(
+    render_instance: &mut terminal::Terminal,
+    map: &NystopiaMap,
+    nekobots: &[Nekobot],
+) -> Result<(), Box<dyn std::error::Error>> {
+    render_instance.render_map(map)?;
+    for nekobot in nekobots {
+        render_instance.place_bot(map, nekobot)?;
+    }
+    render_instance.render_notes()?;
+    Ok(())
+}
+
+/// A line about the bot whose field of view is shown
+fn describe(nekobot: &Nekobot, species: &[BotSpecies]) -> String {
+    let mut caption = format!(
+        "Bot #{} ({}), energy {}, lineage #{}",
+        nekobot.get_id(),
+        species[nekobot.get_species()].name,
+        nekobot.get_energy(),
+        nekobot.get_lineage()
+    );
+    if nekobot.recalls() {
+        caption += &format!(", remembers {} food tiles", nekobot.get_memories());
+    }
+    caption
+}
+
+/// How fast ticks go by, as changed from the keyboard
+struct Pace {
+    // Time between ticks, unless going at full speed
+    period: u128,
+    max_speed: bool,
+    paused: bool,
+    // Whether to go one tick further even though paused
+    step_once: bool,
+}
+
+impl Pace {
+    fn new(period: u128) -> Self {
+        Self {
+            period,
+            max_speed: false,
+            paused: false,
+            step_once: false,
+        }
+    }
+
+    /// Time to wait from one tick to the next
+    fn delay(&self) -> u128 {
+        if self.max_speed {
+            0
+        } else {
+            self.period
+        }
+    }
+
+    /// Pauses, steps or changes the speed if that's what the key is for, returning
+    /// whether it was
+    fn key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('.') => {
+                self.paused = true;
+                self.step_once = true;
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') if !self.max_speed => {
+                // Past a millisecond, ticks stop waiting on the clock
+                if self.period <= 1 {
+                    self.max_speed = true;
+                }
+                self.period /= 2;
+            }
+            KeyCode::Char('-') => {
+                if self.max_speed {
+                    self.max_speed = false;
+                    self.period = 1;
+                } else {
+                    self.period = (self.period * 2).clamp(1, MAX_PERIOD);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Where things are at, followed by how fast they're going, for the bottom of the screen
+    fn status(&self, position: String) -> String {
+        let speed = if self.paused {
+            "paused".into()
+        } else if self.max_speed {
+            "full speed".into()
+        } else {
+            format!("{} ms/tick", self.period)
+        };
+        format!("{}, {}", position, speed)
+    }
+}
+
+/// Picks the next living bot after the one with id `current` (wrapping around) to show
+/// the view of, and returns its id
+fn next_fov_bot(nekobots: &[Nekobot], current: Option<u32>) -> Option<u32> {
+    let start = current
+        .and_then(|id| nekobots.iter().position(|bot| bot.get_id() == id))
+        .map_or(0, |i| i + 1);
+    (0..nekobots.len())
+        .map(|i| &nekobots[(start + i) % nekobots.len()])
+        .find(|bot| bot.is_alive())
+        .map(|bot| bot.get_id())
+}
+
+/// Sets the world up as asked on the command line, `cols` by `rows` tiles unless told
+/// otherwise, or loads it back from a snapshot
+fn build_world(
+    prog_args: &ProgArgs,
+    sim_config: SimConfig,
+    cols: u16,
+    rows: u16,
+) -> Result<World, Box<dyn std::error::Error>> {
+    if let Some(path) = &prog_args.load {
+        return World::load(path);
+    }
+    let mut builder = World::builder()
+        .settings(prog_args.settings.clone())
+        .config(sim_config)
+        .size(prog_args.width.unwrap_or(cols), prog_args.height.unwrap_or(rows));
+    if let Some(seed) = prog_args.seed {
+        builder = builder.seed(seed);
+    }
+    builder.build()
+}
+
+/// Runs the simulation for `--ticks` ticks, or until every bot is dead, without drawing
+/// anything, then sums the run up
+fn run_headless(prog_args: &ProgArgs, sim_config: SimConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut world = build_world(prog_args, sim_config, 80, 24)?;
+    let mut recorder = match &prog_args.record {
+        Some(path) => Some(Recorder::create(path, &world)?),
+        None => None,
+    };
+
+    // A world loaded from a snapshot has been run for a while already
+    let mut stepped: u64 = 0;
+    let inst = Instant::now();
+    while world.get_tick() < prog_args.ticks && world.get_bots().iter().any(|nekobot| nekobot.is_alive()) {
+        world.step();
+        stepped += 1;
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(&world)?;
+        }
+    }
+    let elapsed = inst.elapsed().as_secs_f64();
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.finish()?;
+    }
+
+    let alive = world.get_bots().iter().filter(|nekobot| nekobot.is_alive()).count();
+    print!("{}", world.get_census());
+    println!("Ticks:         {} ({:.0} per second)", world.get_tick(), stepped as f64 / elapsed.max(1e-9));
+    println!("Population:    {} bots alive, {} dead ones still around", alive, world.get_bots().len() - alive);
+    println!("Food eaten:    {} plants", world.get_map().get_plants_eaten());
+    match world.get_mean_lifetime() {
+        Some(lifetime) => println!("Mean lifetime: {:.1} ticks, over {} deaths", lifetime, world.get_deaths()),
+        None => println!("Mean lifetime: - (no deaths)"),
+    }
+    println!("Seed:          {}", world.get_seed());
+    if let Some(path) = &prog_args.save {
+        world.save(path)?;
+    }
+    Ok(())
+}
+
+/// Plays a recorded run back on the terminal, with the same controls over the pace as a
+/// live one, plus seeking
+fn run_replay(prog_args: &ProgArgs, path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut stdout = stdout();
-    let prog_args = ProgArgs::parse();
+    let mut replay = Replay::new(Recording::load(path)?);
+    let (start, end) = replay.get_span();
+
     let mut render_instance = terminal::Terminal::new();
+    render_instance.set_glyph_by(prog_args.glyph_by);
+    render_instance.set_caption(Some(format!("Replay of {}", path)));
+    render_instance.init()?;
 
-    if let Some(logfile) = (&prog_args).log.clone() {
-        WriteLogger::init(LevelFilter::Info, Config::default(), File::create(logfile)?)?;
+    let mut pace = Pace::new(prog_args.tick_delay);
+    let inst = Instant::now();
+    let mut last_capture = inst.elapsed().as_millis();
+    let mut next_stop = last_capture + pace.delay();
+
+    loop {
+        last_capture = inst.elapsed().as_millis();
+        if (last_capture >= next_stop && !pace.paused) || pace.step_once {
+            let frame_end = last_capture + if pace.max_speed && !pace.step_once { FRAME_MSEC } else { 0 };
+            pace.step_once = false;
+            loop {
+                // The replay stops at the end of the recording
+                if !replay.step() {
+                    pace.paused = true;
+                    break;
+                }
+                if inst.elapsed().as_millis() >= frame_end {
+                    break;
+                }
+            }
+            render_instance.set_status(Some(pace.status(format!("Tick {} of {}", replay.get_tick(), end))));
+            redraw(&mut render_instance, replay.get_map(), replay.get_bots())?;
+        }
+        if last_capture >= next_stop {
+            next_stop = last_capture + pace.delay();
+        }
+
+        stdout.flush()?;
+
+        let wait = if pace.paused { FRAME_MSEC } else { next_stop - last_capture };
+        match poll(Duration::from_millis(wait as u64)) {
+            Ok(true) => match read() {
+                Ok(Event::Key(ev)) => {
+                    let step = if ev.modifiers.contains(KeyModifiers::SHIFT) { 10 } else { 1 };
+                    match ev.code {
+                        KeyCode::Char('q') => break,
+                        code if pace.key(code) => next_stop = last_capture + pace.delay(),
+                        KeyCode::Char('[') => replay.seek(replay.get_tick().saturating_sub(SEEK_TICKS).max(start)),
+                        KeyCode::Char(']') => replay.seek(replay.get_tick() + SEEK_TICKS),
+                        KeyCode::Home => replay.seek(start),
+                        KeyCode::End => replay.seek(end),
+                        KeyCode::Char('m') => render_instance.toggle_minimap(),
+                        KeyCode::Char('g') => render_instance.cycle_glyphs(),
+                        KeyCode::Up => render_instance.scroll(replay.get_map(), -step, 0),
+                        KeyCode::Down => render_instance.scroll(replay.get_map(), step, 0),
+                        KeyCode::Left => render_instance.scroll(replay.get_map(), 0, -step),
+                        KeyCode::Right => render_instance.scroll(replay.get_map(), 0, step),
+                        _ => continue,
+                    }
+                    render_instance.set_status(Some(pace.status(format!("Tick {} of {}", replay.get_tick(), end))));
+                    redraw(&mut render_instance, replay.get_map(), replay.get_bots())?;
+                }
+                Ok(Event::Resize(cols, rows)) => {
+                    render_instance.resize(replay.get_map(), cols, rows)?;
+                    redraw(&mut render_instance, replay.get_map(), replay.get_bots())?;
+                }
+                Ok(Event::Mouse(ev)) if render_instance.mouse(replay.get_map(), &ev) => {
+                    redraw(&mut render_instance, replay.get_map(), replay.get_bots())?;
+                }
+                _ => {}
+            },
+            Ok(false) => {}
+            Err(_) => {}
+        }
     }
 
-    let mut nekomap = NystopiaMap::new(
-        &prog_args,
-        render_instance.get_cols()?,
-        render_instance.get_rows()?,
-    )?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = stdout();
+    let prog_args = ProgArgs::parse();
+    let sim_config = match &prog_args.config {
+        Some(path) => SimConfig::load(path)?,
+        None => SimConfig::default(),
+    };
 
-    let mut count = prog_args.bots as usize;
-    let period = prog_args.tick_delay;
+    if let Some(logfile) = prog_args.log.clone() {
+        WriteLogger::init(LevelFilter::Info, Config::default(), File::create(logfile)?)?;
+    }
 
-    let labels = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*()";
+    if let Some(path) = &prog_args.replay {
+        return run_replay(&prog_args, path);
+    }
+    if prog_args.headless {
+        return run_headless(&prog_args, sim_config);
+    }
 
-    let mut nekobots: Vec<Nekobot> = vec![];
+    let mut render_instance = terminal::Terminal::new();
 
-    while count > 0 {
-        let label_index = count % labels.len();
-        nekobots.push(Nekobot::new_rand(
-            format!("{}", labels.get(label_index..(label_index + 1)).unwrap()).as_str(),
-            nekomap.get_rows(),
-            nekomap.get_cols(),
-            &prog_args,
-        ));
-        count -= 1;
+    let mut cols = render_instance.get_cols()?;
+    let rows = render_instance.get_rows()?;
+    if prog_args.settings.neighborhood == Neighborhood::Hex {
+        // Hex tiles are two characters wide, and odd rows are shifted by one more
+        cols = (cols - 1) / 2;
     }
+    let mut world = build_world(&prog_args, sim_config, cols, rows)?;
+    let mut recorder = match &prog_args.record {
+        Some(path) => Some(Recorder::create(path, &world)?),
+        None => None,
+    };
 
+    render_instance.set_glyph_by(prog_args.glyph_by);
     render_instance.init()?;
 
+    // Id of the bot whose field of view is highlighted, if any
+    let mut fov_bot: Option<u32> = None;
+    // Whether every bot has died
+    let mut crashed = false;
+    let mut pace = Pace::new(prog_args.tick_delay);
+
     let inst = Instant::now();
     let mut last_capture = inst.elapsed().as_millis();
-    let mut next_stop = last_capture + period;
+    let mut next_stop = last_capture + pace.delay();
 
     loop {
         last_capture = inst.elapsed().as_millis();
-        if last_capture >= next_stop {
-            // Render the map
-            nekomap.tick_map();
-            render_instance.render_map(&nekomap)?;
-
-            // Render the bots
-            for nekobot in nekobots.iter_mut() {
-                render_instance.place_tile(&nekomap, nekobot.get_row(), nekobot.get_col())?;
-                nekobot.tick(&mut nekomap);
-                render_instance.place_bot(&nekomap, &nekobot)?;
+        if (last_capture >= next_stop && !pace.paused) || pace.step_once {
+            // At full speed, as many ticks as fit in a frame go by between draws
+            let frame_end = last_capture + if pace.max_speed && !pace.step_once { FRAME_MSEC } else { 0 };
+            pace.step_once = false;
+            loop {
+                world.step();
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.record(&world)?;
+                }
+
+                // Let it be known when the population crashes
+                let alive = world.get_bots().iter().any(|nekobot| nekobot.is_alive());
+                if !alive && !crashed {
+                    info!("Population crashed at tick {}", world.get_tick());
+                    pace.paused = pace.paused || prog_args.on_crash == CrashPolicy::Pause;
+                    render_instance.set_message(Some(format!("Every bot died at tick {}", world.get_tick())));
+                } else if alive && crashed {
+                    render_instance.set_message(None);
+                }
+                let changed = crashed == alive;
+                crashed = !alive;
+                if changed || pace.paused || inst.elapsed().as_millis() >= frame_end {
+                    break;
+                }
             }
 
-            next_stop = last_capture + period;
+            let nekobots = world.get_bots();
+            // The bot may have been taken out of the population since
+            let shown = fov_bot.and_then(|id| nekobots.iter().find(|bot| bot.get_id() == id));
+            fov_bot = shown.map(|bot| bot.get_id());
+            if let Some(bot) = shown {
+                render_instance.show_fov(&bot.visible_tiles(world.get_map()));
+            }
+            render_instance.set_caption(shown.map(|bot| describe(bot, world.get_species())));
+            render_instance.set_status(Some(pace.status(format!("Tick {}", world.get_tick()))));
+            redraw(&mut render_instance, world.get_map(), nekobots)?;
+        }
+        if last_capture >= next_stop {
+            next_stop = last_capture + pace.delay();
         }
 
         // Flush the output buffer
         stdout.flush()?;
 
-        match poll(Duration::from_millis((next_stop - last_capture) as u64)) {
+        // While paused, only input needs watching for
+        let wait = if pace.paused { FRAME_MSEC } else { next_stop - last_capture };
+        match poll(Duration::from_millis(wait as u64)) {
             Ok(true) => match read() {
                 Ok(Event::Key(ev)) => {
-                    if ev.code == KeyCode::Char('q') {
-                        break;
+                    let (nekomap, nekobots) = (world.get_map(), world.get_bots());
+                    // Shift scrolls the view ten tiles at a time
+                    let step = if ev.modifiers.contains(KeyModifiers::SHIFT) { 10 } else { 1 };
+                    match ev.code {
+                        KeyCode::Char('q') => break,
+                        code if pace.key(code) => next_stop = last_capture + pace.delay(),
+                        KeyCode::Char('s') => {
+                            if let Some(path) = &prog_args.save {
+                                let note = match world.save(path) {
+                                    Ok(()) => format!("Saved tick {} to {}", world.get_tick(), path),
+                                    Err(err) => format!("Could not save to {}: {}", path, err),
+                                };
+                                render_instance.set_message(Some(note));
+                            }
+                        }
+                        KeyCode::Char('m') => render_instance.toggle_minimap(),
+                        KeyCode::Char('g') => render_instance.cycle_glyphs(),
+                        KeyCode::Char('v') => {
+                            fov_bot = match fov_bot {
+                                Some(_) => None,
+                                None => next_fov_bot(nekobots, None),
+                            }
+                        }
+                        KeyCode::Char('n') if fov_bot.is_some() => {
+                            fov_bot = next_fov_bot(nekobots, fov_bot);
+                        }
+                        KeyCode::Up => render_instance.scroll(nekomap, -step, 0),
+                        KeyCode::Down => render_instance.scroll(nekomap, step, 0),
+                        KeyCode::Left => render_instance.scroll(nekomap, 0, -step),
+                        KeyCode::Right => render_instance.scroll(nekomap, 0, step),
+                        _ => continue,
                     }
+                    let shown = fov_bot.and_then(|id| nekobots.iter().find(|bot| bot.get_id() == id));
+                    match shown {
+                        Some(bot) => render_instance.show_fov(&bot.visible_tiles(nekomap)),
+                        None => render_instance.show_fov(&[]),
+                    }
+                    render_instance.set_caption(shown.map(|bot| describe(bot, world.get_species())));
+                    render_instance.set_status(Some(pace.status(format!("Tick {}", world.get_tick()))));
+                    redraw(&mut render_instance, nekomap, nekobots)?;
+                }
+                Ok(Event::Resize(cols, rows)) => {
+                    let (nekomap, nekobots) = (world.get_map(), world.get_bots());
+                    // The world keeps its size, only the view over it changes
+                    render_instance.resize(nekomap, cols, rows)?;
+                    redraw(&mut render_instance, nekomap, nekobots)?;
+                }
+                Ok(Event::Mouse(ev)) if render_instance.mouse(world.get_map(), &ev) => {
+                    redraw(&mut render_instance, world.get_map(), world.get_bots())?;
                 }
                 _ => {}
             },
@@ -115,5 +502,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.finish()?;
+    }
+
+    // Restore the terminal before reporting how the species did
+    drop(render_instance);
+    print!("{}", world.get_census());
+    println!("Seed: {}", world.get_seed());
+    if let Some(path) = &prog_args.save {
+        world.save(path)?;
+    }
+
     Ok(())
 }
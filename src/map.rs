@@ -1,113 +1,899 @@
-use super::ProgArgs;
+use super::config::SimConfig;
+use super::disaster::{Disaster, DisasterKind, DisasterSchedule};
+use super::nekode::NekoDirs;
+use super::plant::PlantSpecies;
+use super::rng::SimRng;
+use super::settings::Settings;
+use log::info;
 use nanorand::Rng;
+use serde::{Deserialize, Serialize};
 
+/// How the edges of the map behave
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    /// The map ends at its borders
+    Bounded,
+    /// The map wraps around at its borders, left-to-right and top-to-bottom
+    Torus,
+}
+
+/// Which tiles count as the neighbors of a tile (where a bot can step to next)
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum Neighborhood {
+    /// The four orthogonal neighbors
+    VonNeumann,
+    /// The four orthogonal neighbors plus the four diagonals
+    Moore,
+    /// Six neighbors on a hexagonal grid, with every odd row shifted half a tile right
+    Hex,
+}
+
+impl Neighborhood {
+    /// The directions a bot can move in, in the order the default nekode senses them
+    pub fn dirs(&self) -> &'static [NekoDirs] {
+        match self {
+            Self::VonNeumann => &[NekoDirs::Up, NekoDirs::Right, NekoDirs::Down, NekoDirs::Left],
+            Self::Moore => &[
+                NekoDirs::Up,
+                NekoDirs::UpRight,
+                NekoDirs::Right,
+                NekoDirs::DownRight,
+                NekoDirs::Down,
+                NekoDirs::DownLeft,
+                NekoDirs::Left,
+                NekoDirs::UpLeft,
+            ],
+            Self::Hex => &[
+                NekoDirs::UpRight,
+                NekoDirs::Right,
+                NekoDirs::DownRight,
+                NekoDirs::DownLeft,
+                NekoDirs::Left,
+                NekoDirs::UpLeft,
+            ],
+        }
+    }
+
+    /// The (row, col) step taken when moving in `dir` from a tile on `row`, or None if
+    /// the direction isn't part of this neighborhood
+    pub fn offset(&self, dir: &NekoDirs, row: u16) -> Option<(i32, i32)> {
+        if *dir == NekoDirs::Here {
+            return Some((0, 0));
+        }
+        if !self.dirs().contains(dir) {
+            return None;
+        }
+
+        // Hex grids use "odd-r" offset coordinates, so the diagonal neighbors of an odd
+        // row sit one column further right than those of an even row
+        let (left, right) = if *self == Self::Hex {
+            let shift = (row % 2) as i32;
+            (shift - 1, shift)
+        } else {
+            (-1, 1)
+        };
+        Some(match dir {
+            NekoDirs::Here => (0, 0),
+            NekoDirs::Up => (-1, 0),
+            NekoDirs::Down => (1, 0),
+            NekoDirs::Left => (0, -1),
+            NekoDirs::Right => (0, 1),
+            NekoDirs::UpLeft => (-1, left),
+            NekoDirs::UpRight => (-1, right),
+            NekoDirs::DownLeft => (1, left),
+            NekoDirs::DownRight => (1, right),
+        })
+    }
+}
+
+/// The ground a tile is made of, which some plant species grow better on
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Terrain {
+    Soil,
+    Marsh,
+    Sand,
+    /// Bare rock, which nothing grows on, bots can't walk over and blocks the view
+    Rock,
+}
+
+/// Number of ticks a tile keeps burning once it catches fire
+const BURN_TIME: u16 = 2;
+
+/// Remains of a dead bot, which scavengers can feed on until it decomposes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Carcass {
+    energy: u16,
+    decay_counter: u16,
+}
+
+/// A call broadcast by a bot, heard by the others around it on the next tick
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub row: u16,
+    pub col: u16,
+    pub channel: u16,
+    pub value: u16,
+    // Id of the bot that sent it, so it doesn't answer its own calls
+    pub sender: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NystopiaTile {
-    has_food: bool,
+    terrain: Terrain,
+    // Index of the plant species growing here, if any
+    plant: Option<usize>,
     eaten: bool,
     regrowth_counter: u16,
-    regrowth_rate: u16,
+    carcass: Option<Carcass>,
+    // Ticks left of the disasters affecting this tile
+    drought: u16,
+    burning: u16,
+    flooded: u16,
 }
 
 impl NystopiaTile {
-    pub fn new(prog_args: &ProgArgs) -> Self {
-        let mut rng = nanorand::tls_rng();
-        if (rng.generate::<u8>() % 100) < prog_args.food_prob {
-            // It's a food tile
+    pub fn new(settings: &Settings, terrain: Terrain, plants: &[PlantSpecies], rng: &mut SimRng) -> Self {
+        if terrain != Terrain::Rock && (rng.generate::<u8>() % 100) < settings.food_prob {
+            // It's a food tile, pick a species favoring those that like this terrain
+            let plant = PlantSpecies::pick(plants, terrain, rng.generate::<u32>());
             Self {
-                has_food: true,
+                terrain,
+                plant: Some(plant),
                 eaten: false,
                 regrowth_counter: 0u16,
-                regrowth_rate: prog_args.regrow_time,
+                carcass: None,
+                drought: 0,
+                burning: 0,
+                flooded: 0,
             }
         } else {
             // It's not a food tile
             Self {
-                has_food: false,
+                terrain,
+                plant: None,
                 eaten: false,
                 regrowth_counter: 0u16,
-                regrowth_rate: 0,
+                carcass: None,
+                drought: 0,
+                burning: 0,
+                flooded: 0,
             }
         }
     }
 
-    pub fn has_food(self: &Self) -> bool {
-        self.has_food
+    pub fn has_food(&self) -> bool {
+        self.plant.is_some()
     }
 
-    pub fn eaten(self: &Self) -> bool {
+    pub fn eaten(&self) -> bool {
         self.eaten
     }
+
+    pub fn get_plant(&self) -> Option<usize> {
+        self.plant
+    }
+
+    pub fn get_terrain(&self) -> Terrain {
+        self.terrain
+    }
+
+    /// Whether plants can take root here
+    pub fn fertile(&self) -> bool {
+        self.terrain != Terrain::Rock
+    }
+
+    /// Whether bots can walk onto this tile
+    pub fn passable(&self) -> bool {
+        self.terrain != Terrain::Rock && self.flooded == 0
+    }
+
+    pub fn in_drought(&self) -> bool {
+        self.drought > 0
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.burning > 0
+    }
+
+    pub fn is_flooded(&self) -> bool {
+        self.flooded > 0
+    }
+
+    /// Whether this tile hides whatever is behind it
+    pub fn blocks_sight(&self) -> bool {
+        self.terrain == Terrain::Rock
+    }
+
+    pub fn has_carcass(&self) -> bool {
+        self.carcass.is_some()
+    }
 }
 
+/// Size of the cells patch centers are sorted into when laying the terrain out, in tiles
+const PATCH_CELL: usize = 16;
+
+/// The terrain of every tile, row by row: that of the nearest patch center (the first one
+/// listed, on a tie). The centers are sorted into a grid of cells, so that only the cells
+/// around a tile need searching, ring by ring, until no farther cell can hold a nearer one.
+fn lay_out_patches(patches: &[(u16, u16, Terrain)], cols: u16, rows: u16) -> Vec<Terrain> {
+    let grid_cols = (cols as usize).div_ceil(PATCH_CELL);
+    let grid_rows = (rows as usize).div_ceil(PATCH_CELL);
+    let mut cells: Vec<Vec<usize>> = vec![vec![]; grid_cols * grid_rows];
+    for (i, (row, col, _)) in patches.iter().enumerate() {
+        cells[*row as usize / PATCH_CELL * grid_cols + *col as usize / PATCH_CELL].push(i);
+    }
+
+    let mut terrain = Vec::with_capacity(cols as usize * rows as usize);
+    for r in 0..rows {
+        for c in 0..cols {
+            let (cell_row, cell_col) = (r as usize / PATCH_CELL, c as usize / PATCH_CELL);
+            // Squared distance and index of the nearest center so far
+            let mut nearest: Option<(i64, usize)> = None;
+            for ring in 0..grid_cols.max(grid_rows) {
+                for gr in cell_row.saturating_sub(ring)..(cell_row + ring + 1).min(grid_rows) {
+                    for gc in cell_col.saturating_sub(ring)..(cell_col + ring + 1).min(grid_cols) {
+                        // Only the cells on the edge of the ring are new
+                        if gr.abs_diff(cell_row) != ring && gc.abs_diff(cell_col) != ring {
+                            continue;
+                        }
+                        for &i in cells[gr * grid_cols + gc].iter() {
+                            let (pr, pc, _) = patches[i];
+                            let drow = pr as i64 - r as i64;
+                            let dcol = pc as i64 - c as i64;
+                            let candidate = (drow * drow + dcol * dcol, i);
+                            if nearest.is_none_or(|best| candidate < best) {
+                                nearest = Some(candidate);
+                            }
+                        }
+                    }
+                }
+                // Centers beyond this ring are more than `ring` cells away
+                let reach = (ring * PATCH_CELL) as i64 + 1;
+                if nearest.is_some_and(|(dist, _)| dist < reach * reach) {
+                    break;
+                }
+            }
+            terrain.push(nearest.map_or(Terrain::Soil, |(_, i)| patches[i].2));
+        }
+    }
+    terrain
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NystopiaMap {
     map: Vec<NystopiaTile>,
     cols: u16,
     rows: u16,
+    topology: Topology,
+    neighborhood: Neighborhood,
+    plants: Vec<PlantSpecies>,
+    carcass_energy: u16,
+    decay_time: u16,
+    // Scent left behind by bots, one value per tile
+    scent: Vec<f32>,
+    scent_diffusion: f32,
+    scent_evaporation: f32,
+    // Signals sent this tick, and the ones sent on the previous tick that can be heard now
+    signals_sent: Vec<Signal>,
+    signals: Vec<Signal>,
+    signal_radius: u16,
+    tick: u64,
+    plants_eaten: u64,
+    disasters: DisasterSchedule,
+    fire_spread: u8,
 }
 
 impl NystopiaMap {
     pub fn new(
-        prog_args: &ProgArgs,
+        settings: &Settings,
         my_cols: u16,
         my_rows: u16,
+        sim_config: &SimConfig,
+        rng: &mut SimRng,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut plants = sim_config.plants.clone();
         let mut new_map = vec![];
 
-        for _ in 0..(my_cols * my_rows) {
-            new_map.push(NystopiaTile::new(prog_args));
+        if plants.is_empty() {
+            plants.push(PlantSpecies::grass(settings.regrow_time));
+        }
+        for species in plants.iter() {
+            info!(
+                "Plant {}: energy {}, regrowth {} ticks, spread {}%",
+                species.name, species.energy, species.regrow_time, species.spread
+            );
+        }
+
+        // Lay the terrain out in patches: every tile takes the terrain of the nearest of
+        // a few randomly scattered patch centers
+        let patches: Vec<(u16, u16, Terrain)> = (0..(my_cols as usize * my_rows as usize / 200 + 1))
+            .map(|_| {
+                let terrain = match rng.generate::<u8>() % 4 {
+                    0 => Terrain::Marsh,
+                    1 => Terrain::Sand,
+                    _ => Terrain::Soil,
+                };
+                (rng.generate::<u16>() % my_rows, rng.generate::<u16>() % my_cols, terrain)
+            })
+            .collect();
+
+        for terrain in lay_out_patches(&patches, my_cols, my_rows) {
+            // Rocks are strewn about regardless of the patches
+            let terrain = if (rng.generate::<u16>() % 1000) < settings.rock_prob as u16 * 10 {
+                Terrain::Rock
+            } else {
+                terrain
+            };
+            new_map.push(NystopiaTile::new(settings, terrain, &plants, rng));
         }
 
         Ok(Self {
             cols: my_cols,
             rows: my_rows,
             map: new_map,
+            topology: settings.topology,
+            neighborhood: settings.neighborhood,
+            plants,
+            carcass_energy: settings.carcass_energy,
+            decay_time: settings.decay_time.max(1),
+            scent: vec![0.0; my_cols as usize * my_rows as usize],
+            scent_diffusion: settings.scent_diffusion.clamp(0.0, 1.0),
+            scent_evaporation: settings.scent_evaporation.clamp(0.0, 1.0),
+            signals_sent: vec![],
+            signals: vec![],
+            signal_radius: settings.signal_radius,
+            tick: 0,
+            plants_eaten: 0,
+            disasters: DisasterSchedule::new(settings, sim_config.disasters.clone()),
+            fire_spread: settings.fire_spread,
         })
     }
 
-    pub fn get_cols(self: &Self) -> u16 {
+    pub fn get_cols(&self) -> u16 {
         self.cols
     }
 
-    pub fn get_rows(self: &Self) -> u16 {
+    pub fn get_rows(&self) -> u16 {
         self.rows
     }
 
-    pub fn get_tile(self: &Self, row: u16, col: u16) -> Option<&NystopiaTile> {
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Number of plants eaten since the start
+    pub fn get_plants_eaten(&self) -> u64 {
+        self.plants_eaten
+    }
+
+    pub fn get_plants(&self) -> &[PlantSpecies] {
+        &self.plants
+    }
+
+    fn index(&self, row: u16, col: u16) -> usize {
+        self.get_cols() as usize * row as usize + col as usize
+    }
+
+    pub fn get_neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// The tile reached by stepping in `dir` from (row, col), if there is one
+    pub fn neighbor(&self, row: u16, col: u16, dir: &NekoDirs) -> Option<(u16, u16)> {
+        let (drow, dcol) = self.neighborhood.offset(dir, row)?;
+        self.wrap(row as i32 + drow, col as i32 + dcol)
+    }
+
+    /// Whether a bot can walk onto a tile
+    pub fn passable(&self, row: u16, col: u16) -> bool {
+        self.get_tile(row, col).is_some_and(|tile| tile.passable())
+    }
+
+    /// Whether `to` can be seen from `from`, i.e. no tile on the straight line between
+    /// them blocks the view. The line is traced with Bresenham's algorithm, and the two
+    /// end tiles themselves never block it.
+    pub fn line_of_sight(&self, from: (u16, u16), to: (u16, u16)) -> bool {
+        let (drow, dcol) = self.delta(from, to);
+        let (steps_row, steps_col) = (drow.abs(), dcol.abs());
+        let (sign_row, sign_col) = (drow.signum(), dcol.signum());
+        let (mut row, mut col) = (0, 0);
+        let mut err = steps_col - steps_row;
+
+        loop {
+            let e2 = 2 * err;
+            if e2 > -steps_row {
+                err -= steps_row;
+                col += sign_col;
+            }
+            if e2 < steps_col {
+                err += steps_col;
+                row += sign_row;
+            }
+            if (row, col) == (drow, dcol) {
+                return true;
+            }
+            let blocked = self
+                .wrap(from.0 as i32 + row, from.1 as i32 + col)
+                .and_then(|(r, c)| self.get_tile(r, c))
+                .is_none_or(|tile| tile.blocks_sight());
+            if blocked {
+                return false;
+            }
+        }
+    }
+
+    /// Resolves a (possibly out-of-range) coordinate to a tile on the map. On a torus
+    /// this always succeeds by wrapping around, otherwise it fails past the borders.
+    pub fn wrap(&self, row: i32, col: i32) -> Option<(u16, u16)> {
+        let rows = self.get_rows() as i32;
+        let cols = self.get_cols() as i32;
+        match self.topology {
+            Topology::Torus => Some((row.rem_euclid(rows) as u16, col.rem_euclid(cols) as u16)),
+            Topology::Bounded => {
+                if (0..rows).contains(&row) && (0..cols).contains(&col) {
+                    Some((row as u16, col as u16))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Shortest (row, col) displacement leading from one tile to another
+    pub fn delta(&self, from: (u16, u16), to: (u16, u16)) -> (i32, i32) {
+        let mut drow = to.0 as i32 - from.0 as i32;
+        let mut dcol = to.1 as i32 - from.1 as i32;
+        if self.topology == Topology::Torus {
+            let rows = self.get_rows() as i32;
+            let cols = self.get_cols() as i32;
+            if drow.abs() * 2 > rows {
+                drow -= drow.signum() * rows;
+            }
+            if dcol.abs() * 2 > cols {
+                dcol -= dcol.signum() * cols;
+            }
+        }
+        (drow, dcol)
+    }
+
+    /// Distance between two tiles, honoring the map topology. This is the straight-line
+    /// distance on square grids and the number of steps apart on a hex grid.
+    pub fn distance(&self, from: (u16, u16), to: (u16, u16)) -> f64 {
+        let (drow, dcol) = self.delta(from, to);
+        if self.neighborhood == Neighborhood::Hex {
+            // Convert the odd-r offset coordinates into cube coordinates
+            let cube = |row: i32, col: i32| {
+                let x = col - (row - (row & 1)) / 2;
+                (x, row, -x - row)
+            };
+            let (row, col) = (from.0 as i32, from.1 as i32);
+            let (ax, ay, az) = cube(row, col);
+            let (bx, by, bz) = cube(row + drow, col + dcol);
+            (ax - bx).abs().max((ay - by).abs()).max((az - bz).abs()) as f64
+        } else {
+            (drow as f64).hypot(dcol as f64)
+        }
+    }
+
+    pub fn get_tile(&self, row: u16, col: u16) -> Option<&NystopiaTile> {
         if row < self.get_rows() && col < self.get_cols() {
-            Some(&self.map[(self.get_cols() * row + col) as usize])
+            Some(&self.map[self.index(row, col)])
         } else {
             None
         }
     }
 
-    pub fn consume(self: &mut Self, row: u16, col: u16) -> bool {
+    /// Puts a recorded tile in place of the one at (row, col)
+    pub(crate) fn set_tile(&mut self, row: u16, col: u16, tile: NystopiaTile) {
         if row < self.get_rows() && col < self.get_cols() {
-            let index = self.get_cols() * row + col;
-            let tile = &mut self.map[index as usize];
-            if tile.has_food && !tile.eaten {
-                tile.eaten = true;
-                tile.regrowth_counter = tile.regrowth_rate;
-                true
-            } else {
-                false
+            let index = self.index(row, col);
+            self.map[index] = tile;
+        }
+    }
+
+    /// Marks the plant on a tile as eaten or grown back, as recorded
+    pub(crate) fn set_eaten(&mut self, row: u16, col: u16, eaten: bool) {
+        if row < self.get_rows() && col < self.get_cols() {
+            let index = self.index(row, col);
+            self.map[index].eaten = eaten;
+        }
+    }
+
+    pub(crate) fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+
+    /// Eats the plant on a tile, returning its species if there was one to eat
+    pub fn consume(&mut self, row: u16, col: u16) -> Option<usize> {
+        if row < self.get_rows() && col < self.get_cols() {
+            let index = self.index(row, col);
+            let tile = &mut self.map[index];
+            match tile.plant {
+                Some(plant) if !tile.eaten => {
+                    tile.eaten = true;
+                    tile.regrowth_counter = self.plants[plant].regrow_time;
+                    self.plants_eaten += 1;
+                    Some(plant)
+                }
+                _ => None,
             }
         } else {
-            false
+            None
+        }
+    }
+
+    pub fn get_scent(&self, row: u16, col: u16) -> f32 {
+        if row < self.get_rows() && col < self.get_cols() {
+            self.scent[self.index(row, col)]
+        } else {
+            0.0
+        }
+    }
+
+    pub fn deposit_scent(&mut self, row: u16, col: u16, amount: f32) {
+        if row < self.get_rows() && col < self.get_cols() {
+            let index = self.index(row, col);
+            self.scent[index] += amount;
+        }
+    }
+
+    pub fn emit(&mut self, signal: Signal) {
+        self.signals_sent.push(signal);
+    }
+
+    /// The signals sent on the previous tick
+    pub fn get_signals(&self) -> &[Signal] {
+        &self.signals
+    }
+
+    /// How loud a signal is at (row, col): its full value at the source, fading out
+    /// linearly to nothing past the signal radius
+    pub fn signal_strength(&self, signal: &Signal, row: u16, col: u16) -> f64 {
+        let dist = self.distance((signal.row, signal.col), (row, col));
+        let reach = self.signal_radius as f64 + 1.0;
+        signal.value as f64 * (1.0 - dist / reach).max(0.0)
+    }
+
+    /// Spreads a share of every tile's scent evenly over its neighbors, then lets some
+    /// of it evaporate
+    fn diffuse_scent(&mut self) {
+        let mut next = vec![0.0; self.scent.len()];
+
+        for r in 0..self.get_rows() {
+            for c in 0..self.get_cols() {
+                let index = self.index(r, c);
+                let scent = self.scent[index];
+                if scent == 0.0 {
+                    continue;
+                }
+                let neighbors: Vec<usize> = self
+                    .neighborhood
+                    .dirs()
+                    .iter()
+                    .filter_map(|dir| self.neighbor(r, c, dir))
+                    .map(|(row, col)| self.index(row, col))
+                    .collect();
+                let spread = if neighbors.is_empty() { 0.0 } else { scent * self.scent_diffusion };
+                next[index] += scent - spread;
+                for neighbor in neighbors.iter() {
+                    next[*neighbor] += spread / neighbors.len() as f32;
+                }
+            }
+        }
+
+        let keep = 1.0 - self.scent_evaporation;
+        for scent in next.iter_mut() {
+            *scent *= keep;
+            // Don't keep track of traces too faint to matter
+            if *scent < 0.001 {
+                *scent = 0.0;
+            }
+        }
+        self.scent = next;
+    }
+
+    /// Leaves the carcass of a bot that died on a tile
+    pub fn drop_carcass(&mut self, row: u16, col: u16) {
+        if let Some(tile) = self.get_tile(row, col) {
+            let index = self.index(row, col);
+            let energy = self.carcass_energy.saturating_add(tile.carcass.as_ref().map_or(0, |c| c.energy));
+            self.map[index].carcass = Some(Carcass {
+                energy,
+                decay_counter: self.decay_time,
+            });
+        }
+    }
+
+    /// Takes up to `bite` energy from a carcass on a tile, returning how much was eaten
+    pub fn scavenge(&mut self, row: u16, col: u16, bite: u16) -> u16 {
+        if row >= self.get_rows() || col >= self.get_cols() {
+            return 0;
+        }
+        let index = self.index(row, col);
+        let tile = &mut self.map[index];
+        let Some(carcass) = tile.carcass.as_mut() else {
+            return 0;
+        };
+        let eaten = carcass.energy.min(bite);
+        carcass.energy -= eaten;
+        if carcass.energy == 0 {
+            tile.carcass = None;
+        }
+        eaten
+    }
+
+    /// Decomposes the carcasses a little more. The nutrients they release fertilize the
+    /// surrounding tiles, speeding up regrowth and sometimes seeding new plants.
+    fn decompose(&mut self, rng: &mut SimRng) {
+        let mut released = vec![];
+
+        for r in 0..self.get_rows() {
+            for c in 0..self.get_cols() {
+                let index = self.index(r, c);
+                let tile = &mut self.map[index];
+                let Some(carcass) = tile.carcass.as_mut() else {
+                    continue;
+                };
+                // Release an even share of what's left over the remaining decay time
+                let nutrients = carcass.energy / carcass.decay_counter.max(1);
+                carcass.energy -= nutrients;
+                carcass.decay_counter = carcass.decay_counter.saturating_sub(1);
+                if carcass.decay_counter == 0 || carcass.energy == 0 {
+                    tile.carcass = None;
+                }
+                if nutrients > 0 {
+                    released.push((r, c, nutrients));
+                }
+            }
+        }
+
+        for (r, c, nutrients) in released {
+            let mut targets = vec![(r, c)];
+            for dir in self.neighborhood.dirs() {
+                if let Some(target) = self.neighbor(r, c, dir) {
+                    targets.push(target);
+                }
+            }
+            for (row, col) in targets {
+                let index = self.index(row, col);
+                let tile = &mut self.map[index];
+                match tile.plant {
+                    _ if tile.drought > 0 => {}
+                    Some(_) if tile.eaten => {
+                        tile.regrowth_counter = tile.regrowth_counter.saturating_sub(nutrients);
+                    }
+                    Some(_) => {}
+                    None => {
+                        // Every nutrient gives a one percent chance of a new plant
+                        if tile.fertile() && (rng.generate::<u16>() % 100) < nutrients {
+                            let plant = PlantSpecies::pick(&self.plants, tile.terrain, rng.generate::<u32>());
+                            tile.plant = Some(plant);
+                            tile.eaten = true;
+                            tile.regrowth_counter = self.plants[plant].regrow_time;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets off a disaster over all the tiles within its radius
+    fn strike(&mut self, disaster: &Disaster, rng: &mut SimRng) {
+        let row = disaster.row.unwrap_or_else(|| rng.generate::<u16>() % self.get_rows());
+        let col = disaster.col.unwrap_or_else(|| rng.generate::<u16>() % self.get_cols());
+        let radius = disaster.radius as i32;
+        let mut affected = 0;
+        for drow in -radius..=radius {
+            for dcol in -radius..=radius {
+                let Some((r, c)) = self.wrap(row as i32 + drow, col as i32 + dcol) else {
+                    continue;
+                };
+                if self.distance((row, col), (r, c)) > disaster.radius as f64 {
+                    continue;
+                }
+                let index = self.index(r, c);
+                let tile = &mut self.map[index];
+                affected += 1;
+                match disaster.kind {
+                    DisasterKind::Drought => tile.drought = tile.drought.max(disaster.duration),
+                    DisasterKind::Flood => tile.flooded = tile.flooded.max(disaster.duration),
+                    DisasterKind::Fire => {
+                        if tile.plant.is_some() && !tile.eaten {
+                            tile.burning = BURN_TIME;
+                        }
+                    }
+                }
+            }
+        }
+
+        match disaster.kind {
+            DisasterKind::Fire => info!(
+                "Tick {}: {} at ({}, {}), radius {} ({} tiles)",
+                self.tick, disaster.kind, row, col, disaster.radius, affected
+            ),
+            _ => info!(
+                "Tick {}: {} at ({}, {}), radius {} ({} tiles), for {} ticks",
+                self.tick, disaster.kind, row, col, disaster.radius, affected, disaster.duration
+            ),
+        }
+    }
+
+    /// Starts the disasters due this tick, then lets the ongoing ones run their course:
+    /// fires burn down the plants they're on and spread to the neighboring ones, while
+    /// droughts and floods wear off
+    fn tick_disasters(&mut self, rng: &mut SimRng) {
+        let rolls = [(); 3].map(|_| rng.generate::<u16>() % 1000);
+        for disaster in self.disasters.due(self.tick, rolls) {
+            self.strike(&disaster, rng);
+        }
+
+        let mut ignited = vec![];
+        for r in 0..self.get_rows() {
+            for c in 0..self.get_cols() {
+                let index = self.index(r, c);
+                let tile = &mut self.map[index];
+                tile.drought = tile.drought.saturating_sub(1);
+                tile.flooded = tile.flooded.saturating_sub(1);
+                if tile.burning == 0 {
+                    continue;
+                }
+                tile.burning -= 1;
+
+                // Burnt down to the roots, it has to grow back from scratch
+                if let Some(plant) = tile.plant {
+                    tile.eaten = true;
+                    tile.regrowth_counter = self.plants[plant].regrow_time;
+                }
+                for dir in self.neighborhood.dirs() {
+                    if let Some(target) = self.neighbor(r, c, dir) {
+                        if (rng.generate::<u8>() % 100) < self.fire_spread {
+                            ignited.push(target);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (r, c) in ignited {
+            let index = self.index(r, c);
+            let tile = &mut self.map[index];
+            if tile.plant.is_some() && !tile.eaten && tile.burning == 0 {
+                tile.burning = BURN_TIME;
+            }
         }
     }
 
-    pub fn tick_map(self: &mut Self) {
+    pub fn tick_map(&mut self, rng: &mut SimRng) {
+        self.tick += 1;
+        self.signals = std::mem::take(&mut self.signals_sent);
+        self.tick_disasters(rng);
+        self.decompose(rng);
+        self.diffuse_scent();
+
+        let mut seeds = vec![];
+
         for r in 0..self.get_rows() {
             for c in 0..self.get_cols() {
-                let index = self.get_cols() * r + c;
-                let tile = &mut self.map[index as usize];
-                if tile.has_food && tile.eaten {
-                    if tile.regrowth_counter == 1 {
+                let index = self.index(r, c);
+                let tile = &mut self.map[index];
+                let Some(plant) = tile.plant else {
+                    continue;
+                };
+                // Nothing grows during a drought
+                if tile.drought > 0 {
+                    continue;
+                }
+                if tile.eaten {
+                    if tile.regrowth_counter <= 1 {
                         tile.eaten = false;
                     }
-                    tile.regrowth_counter -= 1;
+                    tile.regrowth_counter = tile.regrowth_counter.saturating_sub(1);
+                    continue;
+                }
+
+                // Grown plants spread to a random neighbor now and then
+                let roll = (rng.generate::<u32>() % 10000) as f32 / 100.0;
+                if roll < self.plants[plant].spread {
+                    let dirs = self.neighborhood.dirs();
+                    let dir = &dirs[rng.generate::<usize>() % dirs.len()];
+                    if let Some(target) = self.neighbor(r, c, dir) {
+                        seeds.push((target, plant));
+                    }
                 }
             }
         }
+
+        // Seeds only take root on free tiles, more readily on the terrain they favor
+        for ((r, c), plant) in seeds {
+            let index = self.index(r, c);
+            let species = &self.plants[plant];
+            let tile = &mut self.map[index];
+            if tile.plant.is_none() && tile.fertile() && tile.drought == 0 && rng.generate::<u32>() % 8 < species.affinity(tile.terrain) {
+                // A seedling has to grow before it can be eaten
+                tile.plant = Some(plant);
+                tile.eaten = true;
+                tile.regrowth_counter = species.regrow_time;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(topology: Topology, neighborhood: Neighborhood) -> NystopiaMap {
+        let settings = Settings {
+            topology,
+            neighborhood,
+            ..Settings::default()
+        };
+        NystopiaMap::new(&settings, 10, 10, &SimConfig::default(), &mut SimRng::new(1)).unwrap()
+    }
+
+    #[test]
+    fn distances_wrap_around_a_torus() {
+        let bounded = map(Topology::Bounded, Neighborhood::VonNeumann);
+        assert_eq!(bounded.distance((0, 0), (0, 9)), 9.0);
+        assert_eq!(bounded.distance((0, 0), (3, 4)), 5.0);
+        assert_eq!(bounded.wrap(-1, 0), None);
+
+        let torus = map(Topology::Torus, Neighborhood::VonNeumann);
+        assert_eq!(torus.distance((0, 0), (0, 9)), 1.0);
+        assert_eq!(torus.distance((0, 0), (9, 9)), 2f64.sqrt());
+        assert_eq!(torus.distance((2, 2), (7, 2)), 5.0);
+        assert_eq!(torus.wrap(-1, 10), Some((9, 0)));
+    }
+
+    #[test]
+    fn offsets_follow_the_neighborhood() {
+        assert_eq!(Neighborhood::VonNeumann.offset(&NekoDirs::Up, 0), Some((-1, 0)));
+        assert_eq!(Neighborhood::VonNeumann.offset(&NekoDirs::UpLeft, 0), None);
+        assert_eq!(Neighborhood::Moore.offset(&NekoDirs::UpLeft, 1), Some((-1, -1)));
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::Here, 3), Some((0, 0)));
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::Up, 0), None);
+        // Odd rows are shifted half a tile right
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::UpLeft, 0), Some((-1, -1)));
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::UpRight, 0), Some((-1, 0)));
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::DownLeft, 1), Some((1, 0)));
+        assert_eq!(Neighborhood::Hex.offset(&NekoDirs::DownRight, 1), Some((1, 1)));
+    }
+
+    #[test]
+    fn hex_distances_count_steps() {
+        let hex = map(Topology::Bounded, Neighborhood::Hex);
+        assert_eq!(hex.distance((0, 0), (1, 0)), 1.0);
+        assert_eq!(hex.distance((1, 0), (0, 1)), 1.0);
+        assert_eq!(hex.distance((0, 0), (0, 3)), 3.0);
+        assert_eq!(hex.distance((0, 0), (4, 2)), 4.0);
+
+        let torus = map(Topology::Torus, Neighborhood::Hex);
+        assert_eq!(torus.distance((0, 0), (9, 0)), 1.0);
+        assert_eq!(torus.distance((0, 0), (0, 9)), 1.0);
+        // Every neighbor is one step away, even across the edges
+        for dir in Neighborhood::Hex.dirs() {
+            let there = torus.neighbor(0, 0, dir).unwrap();
+            assert_eq!(torus.distance((0, 0), there), 1.0);
+        }
+    }
+
+    #[test]
+    fn rocks_block_the_view() {
+        let settings = Settings::default();
+        let mut map = map(Topology::Bounded, Neighborhood::VonNeumann);
+        let rock = NystopiaTile::new(&settings, Terrain::Rock, map.get_plants(), &mut SimRng::new(1));
+        map.set_tile(5, 5, rock);
+
+        assert!(map.line_of_sight((5, 2), (5, 4)));
+        assert!(!map.line_of_sight((5, 2), (5, 8)));
+        assert!(!map.line_of_sight((3, 3), (7, 7)));
+        // The rock itself can be seen, and seen past in other directions
+        assert!(map.line_of_sight((5, 2), (5, 5)));
+        assert!(map.line_of_sight((4, 2), (4, 8)));
     }
 }
@@ -0,0 +1,265 @@
+use super::rng::SimRng;
+use nanorand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum NekoDirs {
+    Here,
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl fmt::Display for NekoDirs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Here => write!(f, "Here"),
+            Self::Up => write!(f, "Up"),
+            Self::Down => write!(f, "Down"),
+            Self::Left => write!(f, "Left"),
+            Self::Right => write!(f, "Right"),
+            Self::UpLeft => write!(f, "UpLeft"),
+            Self::UpRight => write!(f, "UpRight"),
+            Self::DownLeft => write!(f, "DownLeft"),
+            Self::DownRight => write!(f, "DownRight"),
+        }
+    }
+}
+
+impl FromStr for NekoDirs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "here" => Ok(Self::Here),
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "upleft" => Ok(Self::UpLeft),
+            "upright" => Ok(Self::UpRight),
+            "downleft" => Ok(Self::DownLeft),
+            "downright" => Ok(Self::DownRight),
+            _ => Err(format!("unknown direction `{}`", s.trim())),
+        }
+    }
+}
+
+/// A single instruction of a bot's program
+#[derive(Clone, Serialize, Deserialize)]
+pub enum NekoOps {
+    /// Score moving in a direction, by how close it leads to food
+    Sense(NekoDirs),
+    /// Score moving in a direction, by how much stronger the scent is there
+    SenseScent(NekoDirs),
+    /// Score moving in a direction, by whether it leads back to remembered food
+    Recall(NekoDirs),
+    /// Move in one of the directions scored so far, picked at random by score
+    Move,
+    Eat,
+    Scavenge,
+    /// Catch a nekobot on the current or a neighboring tile (predators only)
+    Hunt,
+    /// Leave some scent on the current tile
+    Deposit(u16),
+    /// Broadcast a value on a channel, heard by the bots around on the next tick
+    Emit(u16, u16),
+    /// Score moving toward the loudest signal heard on a channel
+    Listen(u16),
+    /// Hand some energy over to the neediest bot on the current or a neighboring tile
+    Give(u16),
+    Halt,
+}
+
+impl NekoOps {
+    /// A random instruction, sensing in one of `dirs` if it needs a direction
+    pub fn random(dirs: &[NekoDirs], rng: &mut SimRng) -> Self {
+        let dir = match rng.generate::<usize>() % (dirs.len() + 1) {
+            0 => NekoDirs::Here,
+            i => dirs[i - 1].clone(),
+        };
+        match rng.generate::<u8>() % 12 {
+            0 => Self::Sense(dir),
+            11 => Self::Recall(dir),
+            1 => Self::SenseScent(dir),
+            2 => Self::Move,
+            3 => Self::Eat,
+            4 => Self::Scavenge,
+            5 => Self::Hunt,
+            6 => Self::Deposit(rng.generate::<u16>() % 20 + 1),
+            7 => Self::Emit(rng.generate::<u16>() % 4, rng.generate::<u16>() % 100 + 1),
+            8 => Self::Listen(rng.generate::<u16>() % 4),
+            9 => Self::Give(rng.generate::<u16>() % 10 + 1),
+            _ => Self::Halt,
+        }
+    }
+}
+
+/// Makes one random change to a program: replaces, inserts or deletes an instruction
+pub fn mutate(ops: &mut Vec<NekoOps>, dirs: &[NekoDirs], rng: &mut SimRng) {
+    let at = rng.generate::<usize>() % (ops.len() + 1);
+    match rng.generate::<u8>() % 3 {
+        0 if at < ops.len() => ops[at] = NekoOps::random(dirs, rng),
+        1 if at < ops.len() && ops.len() > 1 => {
+            ops.remove(at);
+        }
+        _ => ops.insert(at, NekoOps::random(dirs, rng)),
+    }
+}
+
+impl fmt::Display for NekoOps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sense(dir) => write!(f, "Sense({})", dir),
+            Self::SenseScent(dir) => write!(f, "SenseScent({})", dir),
+            Self::Recall(dir) => write!(f, "Recall({})", dir),
+            Self::Move => write!(f, "Move"),
+            Self::Eat => write!(f, "Eat"),
+            Self::Scavenge => write!(f, "Scavenge"),
+            Self::Hunt => write!(f, "Hunt"),
+            Self::Deposit(amount) => write!(f, "Deposit({})", amount),
+            Self::Emit(channel, value) => write!(f, "Emit({}, {})", channel, value),
+            Self::Listen(channel) => write!(f, "Listen({})", channel),
+            Self::Give(amount) => write!(f, "Give({})", amount),
+            Self::Halt => write!(f, "Halt"),
+        }
+    }
+}
+
+impl FromStr for NekoOps {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.split_once('(') {
+            Some((name, rest)) => match rest.strip_suffix(')') {
+                Some(arg) => (name.trim(), Some(arg)),
+                None => return Err(format!("missing `)` in `{}`", s)),
+            },
+            None => (s, None),
+        };
+        let number = |arg: &str| {
+            arg.trim()
+                .parse::<u16>()
+                .map_err(|e| format!("bad number `{}` in `{}`: {}", arg.trim(), s, e))
+        };
+
+        match (name.to_lowercase().as_str(), arg) {
+            ("sense", Some(dir)) => Ok(Self::Sense(dir.parse()?)),
+            ("sensescent", Some(dir)) => Ok(Self::SenseScent(dir.parse()?)),
+            ("recall", Some(dir)) => Ok(Self::Recall(dir.parse()?)),
+            ("move", None) => Ok(Self::Move),
+            ("eat", None) => Ok(Self::Eat),
+            ("scavenge", None) => Ok(Self::Scavenge),
+            ("hunt", None) => Ok(Self::Hunt),
+            ("deposit", Some(amount)) => Ok(Self::Deposit(number(amount)?)),
+            ("emit", Some(args)) => match args.split_once(',') {
+                Some((channel, value)) => Ok(Self::Emit(number(channel)?, number(value)?)),
+                None => Err(format!("`{}` needs a channel and a value", s)),
+            },
+            ("listen", Some(channel)) => Ok(Self::Listen(number(channel)?)),
+            ("give", Some(amount)) => Ok(Self::Give(number(amount)?)),
+            ("halt", None) => Ok(Self::Halt),
+            _ => Err(format!("unknown instruction `{}`", s)),
+        }
+    }
+}
+
+/// A bot's program: a list of instructions run in order every tick
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Nekode(pub Vec<NekoOps>);
+
+impl TryFrom<String> for Nekode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Nekode> for String {
+    fn from(nekode: Nekode) -> Self {
+        nekode.to_string()
+    }
+}
+
+impl fmt::Display for Nekode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ops: Vec<String> = self.0.iter().map(|op| op.to_string()).collect();
+        write!(f, "{}", ops.join(" "))
+    }
+}
+
+impl FromStr for Nekode {
+    type Err = String;
+
+    /// Parses instructions separated by whitespace or semicolons, such as
+    /// `Sense(Here) Sense(Up) Move; Eat`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ops = vec![];
+        let mut token = String::new();
+        let mut depth = 0;
+
+        for ch in s.chars().chain(std::iter::once(';')) {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 && (ch.is_whitespace() || ch == ';') {
+                if !token.trim().is_empty() {
+                    ops.push(token.parse()?);
+                }
+                token.clear();
+            } else {
+                token.push(ch);
+            }
+        }
+
+        if ops.is_empty() {
+            return Err("the program is empty".into());
+        }
+        Ok(Self(ops))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_program() {
+        let nekode: Nekode = "Sense(Up) sense( downleft );Move  Eat; Deposit(3)".parse().unwrap();
+        assert_eq!(nekode.to_string(), "Sense(Up) Sense(DownLeft) Move Eat Deposit(3)");
+        // What it prints parses back to the same program
+        assert_eq!(nekode.to_string().parse::<Nekode>().unwrap().to_string(), nekode.to_string());
+    }
+
+    #[test]
+    fn rejects_bad_programs() {
+        assert!("".parse::<Nekode>().is_err());
+        assert!(" ; ".parse::<Nekode>().is_err());
+        assert!("Jump".parse::<Nekode>().is_err());
+        assert!("Sense(Sideways)".parse::<Nekode>().is_err());
+        assert!("Sense(Up".parse::<Nekode>().is_err());
+        assert!("Move(Up)".parse::<Nekode>().is_err());
+        assert!("Deposit(-1)".parse::<Nekode>().is_err());
+    }
+
+    #[test]
+    fn parses_signals() {
+        let nekode: Nekode = "Listen(3); Emit( 3 ,40 ) Move".parse().unwrap();
+        assert_eq!(nekode.to_string(), "Listen(3) Emit(3, 40) Move");
+        assert!("Emit(3)".parse::<Nekode>().is_err());
+        assert!("Emit(3, x)".parse::<Nekode>().is_err());
+        assert!("Listen".parse::<Nekode>().is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use super::map::Terrain;
+use serde::{Deserialize, Serialize};
+
+/// A kind of vegetation that grows on the map
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlantSpecies {
+    pub name: String,
+
+    /// Energy a bot gains from eating it (before digestion)
+    #[serde(default = "PlantSpecies::default_energy")]
+    pub energy: u8,
+
+    /// Ticks it takes to grow back after being eaten
+    #[serde(default = "PlantSpecies::default_regrow_time")]
+    pub regrow_time: u16,
+
+    /// Chance (in percent) that a grown plant seeds a free neighboring tile each tick
+    #[serde(default)]
+    pub spread: f32,
+
+    /// Terrain the species favors when seeding and spreading
+    #[serde(default)]
+    pub terrain: Option<Terrain>,
+
+    /// Display color as [red, green, blue]
+    #[serde(default)]
+    pub color: Option<(u8, u8, u8)>,
+}
+
+impl PlantSpecies {
+    fn default_energy() -> u8 {
+        20
+    }
+
+    fn default_regrow_time() -> u16 {
+        100
+    }
+
+    /// The vegetation used when no plant species are configured
+    pub fn grass(regrow_time: u16) -> Self {
+        Self {
+            name: "grass".into(),
+            energy: Self::default_energy(),
+            regrow_time,
+            spread: 0.0,
+            terrain: None,
+            color: None,
+        }
+    }
+
+    /// Relative chance of this species taking root on a tile of the given terrain
+    pub fn affinity(&self, terrain: Terrain) -> u32 {
+        match self.terrain {
+            None => 2,
+            Some(preferred) if preferred == terrain => 8,
+            Some(_) => 1,
+        }
+    }
+
+    /// Picks one of the species for a tile of the given terrain from a random roll,
+    /// favoring those that like the terrain
+    pub fn pick(plants: &[PlantSpecies], terrain: Terrain, roll: u32) -> usize {
+        let total: u32 = plants.iter().map(|p| p.affinity(terrain)).sum();
+        let mut pick = roll % total;
+        for (i, species) in plants.iter().enumerate() {
+            if pick < species.affinity(terrain) {
+                return i;
+            }
+            pick -= species.affinity(terrain);
+        }
+        0
+    }
+}
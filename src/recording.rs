@@ -0,0 +1,255 @@
+use super::bot::{Nekobot, NekobotState};
+use super::map::{NystopiaMap, NystopiaTile};
+use super::world::World;
+use bincode::Options;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+
+/// Something that changed over a tick, as far as can be seen on the map
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Event {
+    /// A bot moved to another tile
+    Move { id: u32, row: u16, col: u16 },
+    /// A bot went looking for food, or had its fill
+    Hunger { id: u32, hungry: bool },
+    /// A bot died
+    Death { id: u32 },
+    /// A bot came into the world, at `index` in the population
+    Birth { index: usize, bot: Box<Nekobot> },
+    /// A bot was taken out of the population
+    Removal { id: u32 },
+    /// The plant on a tile was eaten
+    Eat { row: u16, col: u16 },
+    /// The plant on a tile grew back
+    Regrowth { row: u16, col: u16 },
+    /// Anything else about a tile changed: a plant spread or burnt down, a carcass was
+    /// dropped or decomposed, or a disaster struck or passed
+    Tile { row: u16, col: u16, tile: Box<NystopiaTile> },
+}
+
+/// What can be seen of a tile
+fn looks(tile: &NystopiaTile) -> impl PartialEq {
+    (
+        tile.get_terrain(),
+        tile.get_plant(),
+        tile.has_carcass(),
+        tile.in_drought(),
+        tile.is_burning(),
+        tile.is_flooded(),
+    )
+}
+
+/// Recordings are written with variable-length integers, which keeps the events small
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Writes a run to a file as it goes: the map and the bots as they were at the start, then
+/// the events of every tick
+pub struct Recorder {
+    writer: BufWriter<File>,
+    // The tiles and bots as of the last tick recorded, to compare the next one with
+    tiles: Vec<NystopiaTile>,
+    bots: BTreeMap<u32, (u16, u16, NekobotState)>,
+}
+
+impl Recorder {
+    pub fn create(path: &str, world: &World) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        encoding().serialize_into(&mut writer, &(world.get_map(), world.get_bots()))?;
+        info!("Recording from tick {} to {}", world.get_tick(), path);
+
+        let mut recorder = Self {
+            writer,
+            tiles: vec![],
+            bots: BTreeMap::new(),
+        };
+        recorder.events(world);
+        Ok(recorder)
+    }
+
+    /// Writes down what changed in the world since the last tick recorded
+    pub fn record(&mut self, world: &World) -> Result<(), Box<dyn std::error::Error>> {
+        let events = self.events(world);
+        encoding().serialize_into(&mut self.writer, &events)?;
+        Ok(())
+    }
+
+    /// Makes sure everything recorded so far is in the file
+    pub fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// The events that lead from the last tick recorded to the world as it is now
+    fn events(&mut self, world: &World) -> Vec<Event> {
+        let mut events = vec![];
+        let map = world.get_map();
+
+        let mut tiles = Vec::with_capacity(map.get_rows() as usize * map.get_cols() as usize);
+        for row in 0..map.get_rows() {
+            for col in 0..map.get_cols() {
+                let Some(tile) = map.get_tile(row, col) else {
+                    continue;
+                };
+                if let Some(before) = self.tiles.get(tiles.len()) {
+                    if looks(before) != looks(tile) {
+                        events.push(Event::Tile { row, col, tile: Box::new(tile.clone()) });
+                    } else if before.eaten() != tile.eaten() {
+                        events.push(match tile.eaten() {
+                            true => Event::Eat { row, col },
+                            false => Event::Regrowth { row, col },
+                        });
+                    }
+                }
+                tiles.push(tile.clone());
+            }
+        }
+        self.tiles = tiles;
+
+        // Whoever is missing is gone. They're taken out first, so that newborns can then be
+        // put in at the same place in the population as in the world.
+        let ids: BTreeSet<u32> = world.get_bots().iter().map(|bot| bot.get_id()).collect();
+        events.extend(self.bots.keys().filter(|id| !ids.contains(id)).map(|&id| Event::Removal { id }));
+
+        let mut bots = BTreeMap::new();
+        for (index, bot) in world.get_bots().iter().enumerate() {
+            let id = bot.get_id();
+            let now = (bot.get_row(), bot.get_col(), bot.get_state());
+            match self.bots.get(&id).copied() {
+                None => events.push(Event::Birth { index, bot: Box::new(bot.clone()) }),
+                Some((row, col, state)) => {
+                    if (row, col) != (now.0, now.1) {
+                        events.push(Event::Move { id, row: now.0, col: now.1 });
+                    }
+                    if state != now.2 {
+                        events.push(match now.2 {
+                            NekobotState::Dead => Event::Death { id },
+                            state => Event::Hunger { id, hungry: state == NekobotState::Forage },
+                        });
+                    }
+                }
+            }
+            bots.insert(id, now);
+        }
+        self.bots = bots;
+
+        events
+    }
+}
+
+/// A recorded run, read back from a file
+pub struct Recording {
+    map: NystopiaMap,
+    bots: Vec<Nekobot>,
+    // Events of every tick, in order
+    ticks: Vec<Vec<Event>>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let (map, bots): (NystopiaMap, Vec<Nekobot>) = encoding().deserialize_from(&mut reader)?;
+
+        // A run cut short may leave half a tick at the end, which is dropped
+        let mut ticks = vec![];
+        loop {
+            match encoding().deserialize_from(&mut reader) {
+                Ok(events) => ticks.push(events),
+                Err(err) => match *err {
+                    bincode::ErrorKind::Io(ref io) if io.kind() == ErrorKind::UnexpectedEof => break,
+                    _ => return Err(err),
+                },
+            }
+        }
+        info!("Loaded {} ticks from {}", ticks.len(), path);
+
+        Ok(Self { map, bots, ticks })
+    }
+}
+
+/// Plays a recording back, tick by tick, without simulating anything
+pub struct Replay {
+    recording: Recording,
+    map: NystopiaMap,
+    bots: Vec<Nekobot>,
+    // Number of recorded ticks played so far
+    played: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            map: recording.map.clone(),
+            bots: recording.bots.clone(),
+            recording,
+            played: 0,
+        }
+    }
+
+    /// Plays the next tick, unless the recording is over
+    pub fn step(&mut self) -> bool {
+        let Some(events) = self.recording.ticks.get(self.played) else {
+            return false;
+        };
+        for event in events {
+            match event {
+                Event::Move { id, row, col } => {
+                    if let Some(bot) = self.bots.iter_mut().find(|bot| bot.get_id() == *id) {
+                        bot.set_position(*row, *col);
+                    }
+                }
+                Event::Hunger { id, hungry } => {
+                    if let Some(bot) = self.bots.iter_mut().find(|bot| bot.get_id() == *id) {
+                        bot.set_state(if *hungry { NekobotState::Forage } else { NekobotState::Wander });
+                    }
+                }
+                Event::Death { id } => {
+                    if let Some(bot) = self.bots.iter_mut().find(|bot| bot.get_id() == *id) {
+                        bot.set_state(NekobotState::Dead);
+                    }
+                }
+                Event::Birth { index, bot } => self.bots.insert((*index).min(self.bots.len()), *bot.clone()),
+                Event::Removal { id } => self.bots.retain(|bot| bot.get_id() != *id),
+                Event::Eat { row, col } => self.map.set_eaten(*row, *col, true),
+                Event::Regrowth { row, col } => self.map.set_eaten(*row, *col, false),
+                Event::Tile { row, col, tile } => self.map.set_tile(*row, *col, *tile.clone()),
+            }
+        }
+        self.played += 1;
+        self.map.set_tick(self.recording.map.get_tick() + self.played as u64);
+        true
+    }
+
+    /// Jumps to the given tick (or as close as the recording goes), going back to the start
+    /// first if it's already past
+    pub fn seek(&mut self, tick: u64) {
+        if tick < self.get_tick() {
+            self.map = self.recording.map.clone();
+            self.bots = self.recording.bots.clone();
+            self.played = 0;
+        }
+        while self.get_tick() < tick && self.step() {}
+    }
+
+    pub fn get_map(&self) -> &NystopiaMap {
+        &self.map
+    }
+
+    pub fn get_bots(&self) -> &[Nekobot] {
+        &self.bots
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.map.get_tick()
+    }
+
+    /// Ticks the recording starts and ends at
+    pub fn get_span(&self) -> (u64, u64) {
+        let start = self.recording.map.get_tick();
+        (start, start + self.recording.ticks.len() as u64)
+    }
+}
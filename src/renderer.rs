@@ -3,19 +3,25 @@ use super::map::NystopiaMap;
 
 pub trait Renderer {
     fn new() -> Self;
-    fn init(self: &mut Self) -> Result<(), Box<dyn std::error::Error>>;
-    fn blank(self: &mut Self) -> Result<(), Box<dyn std::error::Error>>;
-    fn render_map(self: &mut Self, map: &NystopiaMap) -> Result<(), Box<dyn std::error::Error>>;
-    fn get_rows(self: &Self) -> Result<u16, Box<dyn std::error::Error>>;
-    fn get_cols(self: &Self) -> Result<u16, Box<dyn std::error::Error>>;
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    fn blank(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    fn render_map(&mut self, map: &NystopiaMap) -> Result<(), Box<dyn std::error::Error>>;
+    fn get_rows(&self) -> Result<u16, Box<dyn std::error::Error>>;
+    fn get_cols(&self) -> Result<u16, Box<dyn std::error::Error>>;
+    /// Lays the view out again for a new screen size (in characters)
+    fn resize(&mut self, map: &NystopiaMap, cols: u16, rows: u16) -> Result<(), Box<dyn std::error::Error>>;
+    /// Highlights the tiles in a bot's field of view (nothing, if empty)
+    fn show_fov(&mut self, tiles: &[(u16, u16)]);
+    /// Moves the visible part of the world by the given number of rows and columns
+    fn scroll(&mut self, map: &NystopiaMap, drow: i32, dcol: i32);
     fn place_tile(
-        self: &mut Self,
+        &mut self,
         map: &NystopiaMap,
         row: u16,
         col: u16,
     ) -> Result<(), Box<dyn std::error::Error>>;
     fn place_bot(
-        self: &mut Self,
+        &mut self,
         map: &NystopiaMap,
         bot: &Nekobot,
     ) -> Result<(), Box<dyn std::error::Error>>;
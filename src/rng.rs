@@ -0,0 +1,29 @@
+use nanorand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The random number generator everything in the simulation draws from, so that a run
+/// can be reproduced from its seed. It is WyRand, as in `nanorand`, but keeps its state
+/// where it can be read back.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A fresh seed from the system's source of entropy
+    pub fn random_seed() -> u64 {
+        nanorand::tls_rng().generate()
+    }
+}
+
+impl Rng<8> for SimRng {
+    fn rand(&mut self) -> [u8; 8] {
+        self.state = self.state.wrapping_add(0xa0761d6478bd642f);
+        let t = (self.state as u128).wrapping_mul((self.state ^ 0xe7037ed1a0b428db) as u128);
+        (((t >> 64) ^ t) as u64).to_ne_bytes()
+    }
+}
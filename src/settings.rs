@@ -0,0 +1,208 @@
+use super::bot::{Collision, Kin};
+use super::contest::ContestRule;
+use super::immigration::ArrivalSite;
+use super::lifecycle::DeadPolicy;
+use super::map::{Neighborhood, Topology};
+use super::nekode::Nekode;
+use serde::{Deserialize, Serialize};
+
+/// Everything that shapes how a world is set up and how it runs. With the `cli` feature,
+/// these double as command line options.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct Settings {
+    /// Number of bots to create
+    #[cfg_attr(feature = "cli", arg(short, long, default_value_t = 10, value_name = "BOTS"))]
+    pub bots: u32,
+
+    /// Sight (how many squares ahead a bot can "see")
+    #[cfg_attr(feature = "cli", arg(short, long, default_value_t = 10, value_name = "SQUARES"))]
+    pub sight: u16,
+
+    /// Vegetation regrowth time (in ticks)
+    #[cfg_attr(feature = "cli", arg(short, long, default_value_t = 100, value_name = "TICKS"))]
+    pub regrow_time: u16,
+
+    /// Map vegetation probability (in percent)
+    #[cfg_attr(feature = "cli", arg(short, long, default_value_t = 5, value_name = "PERCENT"))]
+    pub food_prob: u8,
+
+    /// World topology (a torus wraps around at the map edges)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = Topology::Bounded, value_name = "TOPOLOGY"))]
+    pub topology: Topology,
+
+    /// Neighborhood of tiles a bot can sense and move to
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, value_enum, default_value_t = Neighborhood::VonNeumann, value_name = "NEIGHBORHOOD")
+    )]
+    pub neighborhood: Neighborhood,
+
+    /// Energy left in the carcass of a dead bot, for scavengers and the soil
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 30, value_name = "ENERGY"))]
+    pub carcass_energy: u16,
+
+    /// Time it takes a carcass to fully decompose (in ticks)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 60, value_name = "TICKS"))]
+    pub decay_time: u16,
+
+    /// Map rock probability (in percent); rocks block movement and sight
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "PERCENT"))]
+    pub rock_prob: u8,
+
+    /// Share of the scent on a tile that spreads to its neighbors each tick (0 to 1)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0.1, value_name = "FRACTION"))]
+    pub scent_diffusion: f32,
+
+    /// Share of the scent that evaporates each tick (0 to 1)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0.05, value_name = "FRACTION"))]
+    pub scent_evaporation: f32,
+
+    /// Chance of a drought striking each tick (in tenths of a percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "PERMILLE"))]
+    pub drought_rate: u16,
+
+    /// Chance of a fire breaking out each tick (in tenths of a percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "PERMILLE"))]
+    pub fire_rate: u16,
+
+    /// Chance of a flood striking each tick (in tenths of a percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "PERMILLE"))]
+    pub flood_rate: u16,
+
+    /// Radius of the area hit by a random disaster
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 8, value_name = "SQUARES"))]
+    pub disaster_radius: u16,
+
+    /// How long a random drought or flood lasts (in ticks)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 100, value_name = "TICKS"))]
+    pub disaster_duration: u16,
+
+    /// Chance of a fire spreading to each neighboring plant per tick (in percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 40, value_name = "PERCENT"))]
+    pub fire_spread: u8,
+
+    /// Nekode program run by every bot, such as "Sense(Up) Sense(Down) Move Eat"
+    #[cfg_attr(feature = "cli", arg(long, value_name = "NEKODE"))]
+    pub program: Option<Nekode>,
+
+    /// How bots trying to eat the same food in one tick settle who gets it
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ContestRule::Random, value_name = "RULE"))]
+    pub contest: ContestRule,
+
+    /// Energy every bot pays to fight over food (with `--contest fight`)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 5, value_name = "ENERGY"))]
+    pub fight_cost: u8,
+
+    /// What happens when a bot steps onto a tile another bot stands on
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = Collision::Shared, value_name = "RULE"))]
+    pub collision: Collision,
+
+    /// How far a signal sent by a bot can be heard
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 8, value_name = "SQUARES"))]
+    pub signal_radius: u16,
+
+    /// Share of the energy lost when a bot gives it to another (in percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 10, value_name = "PERCENT"))]
+    pub give_loss: u8,
+
+    /// Which bots a bot recognizes as kin, and will give energy to
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = Kin::Off, value_name = "KIN"))]
+    pub kin: Kin,
+
+    /// What becomes of bots once they've been dead for `--dead-time` ticks
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = DeadPolicy::Leave, value_name = "POLICY"))]
+    pub dead: DeadPolicy,
+
+    /// Ticks a dead bot stays around before the dead policy applies
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 20, value_name = "TICKS"))]
+    pub dead_time: u16,
+
+    /// Chance of a new bot arriving each tick (in tenths of a percent)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "PERMILLE"))]
+    pub immigration_rate: u16,
+
+    /// Where arriving bots show up
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ArrivalSite::Random, value_name = "SITE"))]
+    pub arrive_at: ArrivalSite,
+
+    /// Nekode program run by arriving bots (defaults to the one of their species)
+    #[cfg_attr(feature = "cli", arg(long, value_name = "NEKODE"))]
+    pub immigrant_program: Option<Nekode>,
+
+    /// Number of food tiles a bot can remember
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 8, value_name = "TILES"))]
+    pub memory_size: u16,
+
+    /// Ticks it takes a bot to forget about food it saw, trusting the memory less and less
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 200, value_name = "TICKS"))]
+    pub memory_span: u16,
+
+    /// Number of predators to create, which hunt the other bots
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0, value_name = "BOTS"))]
+    pub predators: u32,
+
+    /// Predator sight (how many squares ahead a predator can "see")
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 12, value_name = "SQUARES"))]
+    pub predator_sight: u16,
+
+    /// Number of times a predator runs its program each tick
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 2, value_name = "STEPS"))]
+    pub predator_speed: u8,
+
+    /// Energy level below which a predator starts hunting
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 60, value_name = "ENERGY"))]
+    pub predator_hunger: u8,
+
+    /// Energy a predator gains from each bot it catches
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 50, value_name = "ENERGY"))]
+    pub prey_energy: u8,
+
+    /// Nekode program run by every predator, such as "Sense(Up) Sense(Down) Move Hunt"
+    #[cfg_attr(feature = "cli", arg(long, value_name = "NEKODE"))]
+    pub predator_program: Option<Nekode>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bots: 10,
+            sight: 10,
+            regrow_time: 100,
+            food_prob: 5,
+            topology: Topology::Bounded,
+            neighborhood: Neighborhood::VonNeumann,
+            carcass_energy: 30,
+            decay_time: 60,
+            rock_prob: 0,
+            scent_diffusion: 0.1,
+            scent_evaporation: 0.05,
+            drought_rate: 0,
+            fire_rate: 0,
+            flood_rate: 0,
+            disaster_radius: 8,
+            disaster_duration: 100,
+            fire_spread: 40,
+            program: None,
+            contest: ContestRule::Random,
+            fight_cost: 5,
+            collision: Collision::Shared,
+            signal_radius: 8,
+            give_loss: 10,
+            kin: Kin::Off,
+            dead: DeadPolicy::Leave,
+            dead_time: 20,
+            immigration_rate: 0,
+            arrive_at: ArrivalSite::Random,
+            immigrant_program: None,
+            memory_size: 8,
+            memory_span: 200,
+            predators: 0,
+            predator_sight: 12,
+            predator_speed: 2,
+            predator_hunger: 60,
+            prey_energy: 50,
+            predator_program: None,
+        }
+    }
+}
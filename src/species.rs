@@ -0,0 +1,178 @@
+use super::bot::{Nekobot, NekobotKind};
+use super::nekode::Nekode;
+use super::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Glyphs handed out to the bots of a species without its own
+pub const DEFAULT_GLYPHS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*()";
+
+/// A kind of bot: how many there are at the start, what they run and how they're drawn.
+/// Parameters given as `[min, max]` ranges are rolled for each bot separately.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotSpecies {
+    pub name: String,
+
+    /// Number of bots spawned at the start
+    #[serde(default = "BotSpecies::default_count")]
+    pub count: u32,
+
+    /// What the species feeds on
+    #[serde(default)]
+    pub kind: NekobotKind,
+
+    /// Program run by every bot (defaults to sensing all around, moving and feeding)
+    #[serde(default)]
+    pub program: Option<Nekode>,
+
+    /// Sight range [min, max] (defaults to `--sight`, or `--predator-sight` for carnivores)
+    #[serde(default)]
+    pub sight: Option<(u16, u16)>,
+
+    /// Number of times the program runs each tick, as [min, max]
+    #[serde(default)]
+    pub speed: Option<(u8, u8)>,
+
+    /// Energy level below which a bot goes looking for food, as [min, max]
+    #[serde(default)]
+    pub hunger: Option<(u8, u8)>,
+
+    /// Number of food tiles a bot can remember, as [min, max] (defaults to `--memory-size`)
+    #[serde(default)]
+    pub memory: Option<(u16, u16)>,
+
+    /// Glyphs handed out to the bots in turn
+    #[serde(default)]
+    pub glyphs: Option<String>,
+
+    /// Display color as [red, green, blue]
+    #[serde(default)]
+    pub color: Option<(u8, u8, u8)>,
+}
+
+impl BotSpecies {
+    fn default_count() -> u32 {
+        10
+    }
+
+    /// The species used when none are configured: plain nekobots plus, if asked for,
+    /// predators, all set up from the settings
+    pub fn from_settings(settings: &Settings) -> Vec<Self> {
+        let mut species = vec![Self {
+            name: "nekobots".into(),
+            count: settings.bots,
+            kind: NekobotKind::Herbivore,
+            program: settings.program.clone(),
+            sight: None,
+            speed: None,
+            hunger: None,
+            memory: None,
+            glyphs: None,
+            color: None,
+        }];
+        if settings.predators > 0 {
+            species.push(Self {
+                name: "predators".into(),
+                count: settings.predators,
+                kind: NekobotKind::Carnivore,
+                program: settings.predator_program.clone(),
+                sight: None,
+                speed: None,
+                hunger: None,
+                memory: None,
+                glyphs: Some("@".into()),
+                color: None,
+            });
+        }
+        species
+    }
+
+    /// The glyph of the bot numbered `n`
+    pub fn glyph(&self, n: usize) -> char {
+        let glyphs: Vec<char> = self.glyphs.as_deref().unwrap_or(DEFAULT_GLYPHS).chars().collect();
+        match glyphs.len() {
+            0 => '?',
+            len => glyphs[n % len],
+        }
+    }
+}
+
+/// Keeps track of how every species fares over a run
+#[derive(Serialize, Deserialize)]
+pub struct Census {
+    names: Vec<String>,
+    start: Vec<usize>,
+    alive: Vec<usize>,
+    peak: Vec<usize>,
+    extinct_at: Vec<Option<u64>>,
+}
+
+impl Census {
+    pub fn new(species: &[BotSpecies], bots: &[Nekobot]) -> Self {
+        let mut census = Self {
+            names: species.iter().map(|s| s.name.clone()).collect(),
+            start: vec![0; species.len()],
+            alive: vec![0; species.len()],
+            peak: vec![0; species.len()],
+            extinct_at: vec![None; species.len()],
+        };
+        census.update(0, bots);
+        census.start = census.alive.clone();
+        census
+    }
+
+    /// Counts the living bots of each species as of `tick`
+    pub fn update(&mut self, tick: u64, bots: &[Nekobot]) {
+        self.alive.iter_mut().for_each(|alive| *alive = 0);
+        for bot in bots.iter().filter(|bot| bot.is_alive()) {
+            self.alive[bot.get_species()] += 1;
+        }
+        for i in 0..self.names.len() {
+            self.peak[i] = self.peak[i].max(self.alive[i]);
+            // Species only die out once they've been around, and may come back through
+            // immigration
+            if self.alive[i] > 0 {
+                self.extinct_at[i] = None;
+            } else if self.peak[i] > 0 && self.extinct_at[i].is_none() {
+                self.extinct_at[i] = Some(tick);
+            }
+        }
+    }
+
+    /// The species that did best: the most survivors, or failing that, the one that held
+    /// out the longest. Several of them if they're tied, and species that never had any
+    /// bots don't count.
+    pub fn winners(&self) -> Vec<usize> {
+        let score = |i: usize| (self.alive[i], self.extinct_at[i].unwrap_or(u64::MAX));
+        let contenders: Vec<usize> = (0..self.names.len()).filter(|&i| self.peak[i] > 0).collect();
+        let best = contenders.iter().map(|&i| score(i)).max();
+        contenders.into_iter().filter(|&i| Some(score(i)) == best).collect()
+    }
+}
+
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16} {:>6} {:>6} {:>6}  Extinct", "Species", "Start", "Peak", "Alive")?;
+        for i in 0..self.names.len() {
+            let extinct = match self.extinct_at[i] {
+                Some(tick) => format!("at tick {}", tick),
+                None => "-".into(),
+            };
+            writeln!(
+                f,
+                "{:<16} {:>6} {:>6} {:>6}  {}",
+                self.names[i], self.start[i], self.peak[i], self.alive[i], extinct
+            )?;
+        }
+        if self.names.len() > 1 {
+            let winners: Vec<&str> = self.winners().iter().map(|&i| self.names[i].as_str()).collect();
+            match winners.len() {
+                0 => {}
+                1 => writeln!(f, "Winner: {}", winners[0])?,
+                _ => writeln!(f, "Tie: {}", winners.join(", "))?,
+            }
+        }
+        Ok(())
+    }
+}
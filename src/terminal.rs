@@ -1,130 +1,522 @@
-use super::bot::{Nekobot, NekobotState};
-use super::map::NystopiaMap;
-use super::renderer::Renderer;
+use nekobots::bot::{Nekobot, NekobotKind, NekobotState};
+use nekobots::map::{Neighborhood, NystopiaMap, NystopiaTile, Terrain};
+use nekobots::renderer::Renderer;
+use nekobots::species::DEFAULT_GLYPHS;
+use clap::ValueEnum;
 use crossterm::{
-    cursor, execute, queue,
+    cursor,
+    event::{DisableMouseCapture, EnableMouseCapture, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    execute, queue,
     style::{self, Color},
     terminal as term,
 };
+use std::collections::HashSet;
 use std::io::{stdout, Stdout, Write};
 
+/// What the glyph a bot is drawn with tells about it
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum GlyphBy {
+    /// Its species, with every bot of it getting one of the species' glyphs
+    Species,
+    /// Whether it is hungry, and what it feeds on
+    State,
+    /// Its lineage, shared by all bots descending from the same founder
+    Lineage,
+}
+
 pub struct Terminal {
     stdout: Stdout,
+    cols: u16,
+    rows: u16,
+    // World tile shown in the top-left corner of the screen
+    view_row: u16,
+    view_col: u16,
+    show_minimap: bool,
+    // Last screen position of a mouse drag over the map
+    drag_from: Option<(u16, u16)>,
+    // Tiles highlighted as seen by the selected bot
+    fov: HashSet<(u16, u16)>,
+    // Note shown on the bottom line of the screen
+    message: Option<String>,
+    // Note shown on the top line of the screen
+    caption: Option<String>,
+    // Note shown at the right end of the bottom line
+    status: Option<String>,
+    glyph_by: GlyphBy,
+}
+
+impl Terminal {
+    /// Hex tiles are drawn two characters wide, with odd rows indented by one character
+    /// so that each tile sits between the two tiles above and below it
+    fn tile_width(map: &NystopiaMap) -> u16 {
+        match map.get_neighborhood() {
+            Neighborhood::Hex => 2,
+            _ => 1,
+        }
+    }
+
+    /// Grown plants are drawn in the color of their species, anything else shows a
+    /// faint tint of the terrain underneath
+    fn tile_color(map: &NystopiaMap, tile: &NystopiaTile) -> Color {
+        if tile.is_burning() {
+            return Color::Rgb { r: 220, g: 80, b: 0 };
+        }
+        if tile.is_flooded() {
+            return Color::DarkBlue;
+        }
+        match tile.get_plant() {
+            Some(plant) if !tile.eaten() => match map.get_plants()[plant].color {
+                Some((r, g, b)) => Color::Rgb { r, g, b },
+                None => Color::DarkGreen,
+            },
+            _ if tile.in_drought() => Color::Rgb { r: 60, g: 40, b: 10 },
+            _ => match tile.get_terrain() {
+                Terrain::Soil => Color::Black,
+                Terrain::Marsh => Color::Rgb { r: 0, g: 10, b: 30 },
+                Terrain::Sand => Color::Rgb { r: 30, g: 25, b: 0 },
+                Terrain::Rock => Color::Rgb { r: 90, g: 90, b: 90 },
+            },
+        }
+    }
+
+    /// Background of a tile, lit up when it is in the field of view being shown
+    fn background(&self, map: &NystopiaMap, tile: &NystopiaTile, row: u16, col: u16) -> Color {
+        if !self.fov.contains(&(row, col)) {
+            return Terminal::tile_color(map, tile);
+        }
+        match tile.get_plant() {
+            Some(_) if !tile.eaten() => Color::Green,
+            _ if tile.blocks_sight() => Color::Rgb { r: 140, g: 140, b: 160 },
+            _ => Color::Rgb { r: 40, g: 40, b: 70 },
+        }
+    }
+
+    /// What is drawn on a tile when there's no bot on it
+    fn tile_glyph(map: &NystopiaMap, tile: &NystopiaTile) -> String {
+        let glyph = if tile.has_carcass() { "%" } else { " " };
+        format!("{:width$}", glyph, width = Terminal::tile_width(map) as usize)
+    }
+
+    /// Number of (rows, cols) of the world that fit on the screen at once
+    fn view_size(&self, map: &NystopiaMap) -> (u16, u16) {
+        let width = Terminal::tile_width(map);
+        let cols = self.cols.saturating_sub(width - 1) / width;
+        (self.rows.min(map.get_rows()), cols.min(map.get_cols()))
+    }
+
+    /// Keeps the viewport from scrolling past the edges of the world
+    fn clamp_view(&mut self, map: &NystopiaMap) {
+        let (rows, cols) = self.view_size(map);
+        self.view_row = self.view_row.min(map.get_rows() - rows);
+        self.view_col = self.view_col.min(map.get_cols() - cols);
+    }
+
+    /// Screen rectangle (x, y, width, height) of the minimap, if it is shown. There's
+    /// no minimap when the whole world already fits on the screen.
+    fn minimap_rect(&self, map: &NystopiaMap) -> Option<(u16, u16, u16, u16)> {
+        let (rows, cols) = self.view_size(map);
+        if !self.show_minimap || (rows >= map.get_rows() && cols >= map.get_cols()) {
+            return None;
+        }
+        let width = map.get_cols().min((self.cols / 4).max(10)).min(self.cols);
+        let height = map.get_rows().min((self.rows / 4).max(5)).min(self.rows);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some((self.cols - width, 0, width, height))
+    }
+
+    /// Screen position of a world tile, or None if it is scrolled out of view or hidden
+    /// behind the minimap
+    fn screen_pos(&self, map: &NystopiaMap, row: u16, col: u16) -> Option<(u16, u16)> {
+        let (rows, cols) = self.view_size(map);
+        if row < self.view_row
+            || col < self.view_col
+            || row >= self.view_row + rows
+            || col >= self.view_col + cols
+        {
+            return None;
+        }
+
+        let width = Terminal::tile_width(map);
+        let x = (col - self.view_col) * width + if width > 1 { row % 2 } else { 0 };
+        let y = row - self.view_row;
+
+        if let Some((mx, my, mw, mh)) = self.minimap_rect(map) {
+            if x + width > mx && x < mx + mw && y >= my && y < my + mh {
+                return None;
+            }
+        }
+        Some((x, y))
+    }
+
+    /// World region (first row, first col, rows, cols) covered by one minimap cell
+    fn minimap_cell(map: &NystopiaMap, rect: (u16, u16, u16, u16), x: u16, y: u16) -> (u16, u16, u16, u16) {
+        let (_, _, mw, mh) = rect;
+        let row = (y as u32 * map.get_rows() as u32 / mh as u32) as u16;
+        let col = (x as u32 * map.get_cols() as u32 / mw as u32) as u16;
+        let next_row = ((y as u32 + 1) * map.get_rows() as u32 / mh as u32) as u16;
+        let next_col = ((x as u32 + 1) * map.get_cols() as u32 / mw as u32) as u16;
+        (row, col, (next_row - row).max(1), (next_col - col).max(1))
+    }
+
+    /// Color of a minimap cell: green if there is food in the world region it covers,
+    /// highlighted when that region is currently in view
+    fn minimap_color(&self, map: &NystopiaMap, rect: (u16, u16, u16, u16), x: u16, y: u16) -> Color {
+        let (row, col, height, width) = Terminal::minimap_cell(map, rect, x, y);
+        let (rows, cols) = self.view_size(map);
+        let mut food = false;
+        for r in row..(row + height) {
+            for c in col..(col + width) {
+                if let Some(tile) = map.get_tile(r, c) {
+                    food |= tile.has_food() && !tile.eaten();
+                }
+            }
+        }
+        let in_view = row + height > self.view_row
+            && row < self.view_row + rows
+            && col + width > self.view_col
+            && col < self.view_col + cols;
+        match (food, in_view) {
+            (true, true) => Color::Green,
+            (true, false) => Color::DarkGreen,
+            (false, true) => Color::DarkGrey,
+            (false, false) => Color::Black,
+        }
+    }
+
+    fn render_minimap(&mut self, map: &NystopiaMap) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(rect) = self.minimap_rect(map) else {
+            return Ok(());
+        };
+        let (mx, my, mw, mh) = rect;
+
+        for y in 0..mh {
+            queue!(self.stdout, cursor::MoveTo(mx, my + y))?;
+            for x in 0..mw {
+                let color = self.minimap_color(map, rect, x, y);
+                queue!(self.stdout, style::SetBackgroundColor(color), style::Print(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Screen position and color of the minimap cell that contains a world tile
+    fn minimap_pos(&self, map: &NystopiaMap, row: u16, col: u16) -> Option<(u16, u16, Color)> {
+        let rect = self.minimap_rect(map)?;
+        let (mx, my, mw, mh) = rect;
+        let x = (col as u32 * mw as u32 / map.get_cols() as u32) as u16;
+        let y = (row as u32 * mh as u32 / map.get_rows() as u32) as u16;
+        Some((mx + x, my + y, self.minimap_color(map, rect, x, y)))
+    }
+
+    /// Centers the viewport over the world tile under a minimap cell
+    fn center_on_minimap(&mut self, map: &NystopiaMap, x: u16, y: u16) -> bool {
+        let Some(rect) = self.minimap_rect(map) else {
+            return false;
+        };
+        let (mx, my, mw, mh) = rect;
+        if x < mx || y < my || x >= mx + mw || y >= my + mh {
+            return false;
+        }
+        let (row, col, _, _) = Terminal::minimap_cell(map, rect, x - mx, y - my);
+        let (rows, cols) = self.view_size(map);
+        self.view_row = row.saturating_sub(rows / 2);
+        self.view_col = col.saturating_sub(cols / 2);
+        self.clamp_view(map);
+        true
+    }
+
+    /// Scrolls the viewport in response to the mouse: the wheel scrolls up and down (or
+    /// left and right while holding shift), dragging over the map pans it, and clicking
+    /// on the minimap jumps there. Returns true if the view has changed.
+    pub fn mouse(&mut self, map: &NystopiaMap, ev: &MouseEvent) -> bool {
+        let shift = ev.modifiers.contains(KeyModifiers::SHIFT);
+        match ev.kind {
+            MouseEventKind::ScrollUp if shift => self.scroll(map, 0, -3),
+            MouseEventKind::ScrollDown if shift => self.scroll(map, 0, 3),
+            MouseEventKind::ScrollUp => self.scroll(map, -3, 0),
+            MouseEventKind::ScrollDown => self.scroll(map, 3, 0),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.center_on_minimap(map, ev.column, ev.row) {
+                    self.drag_from = None;
+                    return true;
+                }
+                self.drag_from = Some((ev.column, ev.row));
+                return false;
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if self.center_on_minimap(map, ev.column, ev.row) {
+                    return true;
+                }
+                let Some((x, y)) = self.drag_from.replace((ev.column, ev.row)) else {
+                    return false;
+                };
+                let width = Terminal::tile_width(map) as i32;
+                self.scroll(map, y as i32 - ev.row as i32, (x as i32 - ev.column as i32) / width);
+            }
+            MouseEventKind::Up(_) => {
+                self.drag_from = None;
+                return false;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn toggle_minimap(&mut self) {
+        self.show_minimap = !self.show_minimap;
+    }
+
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+    pub fn set_caption(&mut self, caption: Option<String>) {
+        self.caption = caption;
+    }
+
+    pub fn set_status(&mut self, status: Option<String>) {
+        self.status = status;
+    }
+
+    /// Draws the message and the caption, if any, over the bottom and top lines of the map,
+    /// with the status at the right end of the bottom line
+    pub fn render_notes(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let status: String = self.status.as_deref().unwrap_or("").chars().take(self.cols as usize).collect();
+        let status_len = status.chars().count() as u16;
+        let bottom = self.rows.saturating_sub(1);
+        let notes = [
+            (bottom, 0, self.cols.saturating_sub(status_len + 1), self.message.as_deref()),
+            (0, 0, self.cols, self.caption.as_deref()),
+            (bottom, self.cols - status_len, status_len, Some(status.as_str())),
+        ];
+        for (y, x, width, note) in notes {
+            let Some(note) = note else {
+                continue;
+            };
+            let text: String = note.chars().take(width as usize).collect();
+            queue!(
+                self.stdout,
+                cursor::MoveTo(x, y),
+                style::SetBackgroundColor(Color::Black),
+                style::SetForegroundColor(Color::White),
+                style::Print(text)
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn set_glyph_by(&mut self, glyph_by: GlyphBy) {
+        self.glyph_by = glyph_by;
+    }
+
+    /// Switches to drawing bots by species, state and lineage in turn
+    pub fn cycle_glyphs(&mut self) {
+        self.glyph_by = match self.glyph_by {
+            GlyphBy::Species => GlyphBy::State,
+            GlyphBy::State => GlyphBy::Lineage,
+            GlyphBy::Lineage => GlyphBy::Species,
+        };
+    }
+
+    fn bot_glyph(&self, bot: &Nekobot) -> char {
+        match self.glyph_by {
+            GlyphBy::Species => bot.get_glyph(),
+            GlyphBy::State => match (bot.get_kind(), bot.get_state()) {
+                (NekobotKind::Herbivore, NekobotState::Forage) => 'O',
+                (NekobotKind::Herbivore, _) => 'o',
+                (NekobotKind::Carnivore, NekobotState::Forage) => 'W',
+                (NekobotKind::Carnivore, _) => 'w',
+            },
+            GlyphBy::Lineage => {
+                let glyphs: Vec<char> = DEFAULT_GLYPHS.chars().collect();
+                glyphs[bot.get_lineage() as usize % glyphs.len()]
+            }
+        }
+    }
 }
 
 impl Renderer for Terminal {
     fn new() -> Self {
-        Terminal { stdout: stdout() }
+        let (cols, rows) = term::size().unwrap_or((80, 24));
+        Terminal {
+            stdout: stdout(),
+            cols,
+            rows,
+            view_row: 0,
+            view_col: 0,
+            show_minimap: true,
+            drag_from: None,
+            fov: HashSet::new(),
+            message: None,
+            caption: None,
+            status: None,
+            glyph_by: GlyphBy::Species,
+        }
     }
 
-    fn init(self: &mut Self) -> Result<(), Box<dyn std::error::Error>> {
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         term::enable_raw_mode()?;
+        execute!(self.stdout, EnableMouseCapture)?;
         self.blank()?;
         Ok(())
     }
 
-    fn blank(self: &mut Self) -> Result<(), Box<dyn std::error::Error>> {
+    fn blank(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Clear terminal
-        execute!(self.stdout, term::Clear(term::ClearType::All), cursor::Hide)?;
+        execute!(
+            self.stdout,
+            style::SetBackgroundColor(Color::Black),
+            term::Clear(term::ClearType::All),
+            cursor::Hide
+        )?;
         Ok(())
     }
 
-    fn render_map(self: &mut Self, map: &NystopiaMap) -> Result<(), Box<dyn std::error::Error>> {
-        queue!(self.stdout, cursor::MoveTo(0, 0))?;
+    fn render_map(&mut self, map: &NystopiaMap) -> Result<(), Box<dyn std::error::Error>> {
+        let width = Terminal::tile_width(map);
+        let (rows, cols) = self.view_size(map);
 
-        for r in 0..map.get_rows() {
-            for c in 0..map.get_cols() {
+        for y in 0..rows {
+            let r = self.view_row + y;
+            queue!(self.stdout, cursor::MoveTo(0, y))?;
+            if width > 1 && r % 2 == 1 {
+                queue!(
+                    self.stdout,
+                    style::SetBackgroundColor(Color::Black),
+                    style::Print(" ")
+                )?;
+            }
+            for c in self.view_col..(self.view_col + cols) {
                 if let Some(this_tile) = map.get_tile(r, c) {
-                    if this_tile.has_food() && !this_tile.eaten() {
-                        queue!(
-                            self.stdout,
-                            style::SetBackgroundColor(Color::DarkGreen),
-                            style::Print(" ")
-                        )?;
-                    } else {
-                        queue!(
-                            self.stdout,
-                            style::SetBackgroundColor(Color::Black),
-                            style::Print(" ")
-                        )?;
-                    }
+                    let background = self.background(map, this_tile, r, c);
+                    queue!(
+                        self.stdout,
+                        style::SetBackgroundColor(background),
+                        style::SetForegroundColor(Color::DarkRed),
+                        style::Print(Terminal::tile_glyph(map, this_tile))
+                    )?;
                 }
             }
         }
 
+        self.render_minimap(map)?;
+
         self.stdout.flush()?;
 
         Ok(())
     }
 
-    fn get_rows(self: &Self) -> Result<u16, Box<dyn std::error::Error>> {
-        let (_, rows) = term::size()?;
-        Ok(rows)
+    fn get_rows(&self) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(self.rows)
     }
 
-    fn get_cols(self: &Self) -> Result<u16, Box<dyn std::error::Error>> {
-        let (cols, _) = term::size()?;
-        Ok(cols)
+    fn get_cols(&self) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(self.cols)
+    }
+
+    fn resize(&mut self, map: &NystopiaMap, cols: u16, rows: u16) -> Result<(), Box<dyn std::error::Error>> {
+        self.cols = cols;
+        self.rows = rows;
+        self.drag_from = None;
+        self.clamp_view(map);
+
+        // Whatever was drawn for the old layout is now in the wrong place
+        self.blank()
+    }
+
+    fn show_fov(&mut self, tiles: &[(u16, u16)]) {
+        self.fov = tiles.iter().copied().collect();
+    }
+
+    fn scroll(&mut self, map: &NystopiaMap, drow: i32, dcol: i32) {
+        self.view_row = (self.view_row as i32 + drow).max(0) as u16;
+        self.view_col = (self.view_col as i32 + dcol).max(0) as u16;
+        self.clamp_view(map);
     }
 
     fn place_tile(
-        self: &mut Self,
+        &mut self,
         map: &NystopiaMap,
         row: u16,
         col: u16,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let tile = map
-            .get_tile(row, col)
-            .expect("Failed to identify tile".into());
+        let Some(tile) = map.get_tile(row, col) else {
+            return Ok(());
+        };
+        let Some((x, y)) = self.screen_pos(map, row, col) else {
+            return Ok(());
+        };
+        let background = self.background(map, tile, row, col);
         // Remove the old placements
         queue!(
             self.stdout,
-            cursor::MoveTo(col, row),
-            if !tile.eaten() && tile.has_food() {
-                style::SetBackgroundColor(Color::DarkGreen)
-            } else {
-                style::SetBackgroundColor(Color::Black)
-            },
-            style::Print(" ")
+            cursor::MoveTo(x, y),
+            style::SetBackgroundColor(background),
+            style::SetForegroundColor(Color::DarkRed),
+            style::Print(Terminal::tile_glyph(map, tile))
         )?;
         Ok(())
     }
 
     fn place_bot(
-        self: &mut Self,
+        &mut self,
         map: &NystopiaMap,
         bot: &Nekobot,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let tile = map
-            .get_tile(bot.get_row(), bot.get_col())
-            .expect("Failed to identify tile".into());
+        let Some(tile) = map.get_tile(bot.get_row(), bot.get_col()) else {
+            return Ok(());
+        };
+        let color = match (bot.get_color(), bot.get_kind(), bot.get_state()) {
+            // Dead bots are only seen through the carcass they leave behind
+            (_, _, NekobotState::Dead) => return self.place_tile(map, bot.get_row(), bot.get_col()),
+            // Species with a color of their own turn darker when hungry
+            (Some((r, g, b)), _, NekobotState::Wander) => Color::Rgb { r, g, b },
+            (Some((r, g, b)), _, NekobotState::Forage) => Color::Rgb { r: r / 2, g: g / 2, b: b / 2 },
+            (None, NekobotKind::Herbivore, NekobotState::Wander) => Color::Grey,
+            (None, NekobotKind::Herbivore, NekobotState::Forage) => Color::Yellow,
+            (None, NekobotKind::Carnivore, NekobotState::Wander) => Color::Magenta,
+            (None, NekobotKind::Carnivore, NekobotState::Forage) => Color::Red,
+        };
+
+        // Mark the bot on the minimap
+        if let Some((x, y, background)) = self.minimap_pos(map, bot.get_row(), bot.get_col()) {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(x, y),
+                style::SetBackgroundColor(background),
+                style::SetForegroundColor(color),
+                style::Print("*")
+            )?;
+        }
+
+        let Some((x, y)) = self.screen_pos(map, bot.get_row(), bot.get_col()) else {
+            return Ok(());
+        };
+
+        let background = self.background(map, tile, bot.get_row(), bot.get_col());
+        let glyph = self.bot_glyph(bot);
 
         // Draw the new placements
         queue!(
             self.stdout,
-            cursor::MoveTo(bot.get_col(), bot.get_row()),
-            if !tile.eaten() && tile.has_food() {
-                style::SetBackgroundColor(Color::DarkGreen)
-            } else {
-                style::SetBackgroundColor(Color::Black)
-            },
-            match bot.get_state() {
-                NekobotState::Wander => style::SetForegroundColor(Color::Grey),
-                NekobotState::Forage => style::SetForegroundColor(Color::Yellow),
-                NekobotState::Dead => style::SetForegroundColor(Color::Red),
-            },
-            style::Print(bot.get_label().clone())
+            cursor::MoveTo(x, y),
+            style::SetBackgroundColor(background),
+            style::SetForegroundColor(color),
+            style::Print(glyph)
         )?;
         Ok(())
     }
 }
 
 impl Drop for Terminal {
-    fn drop(self: &mut Self) {
+    fn drop(&mut self) {
+        execute!(self.stdout, DisableMouseCapture).ok();
         term::disable_raw_mode().ok();
         // Put cursor at bottom-left before exit
-        let bottom = self.get_rows().unwrap();
-        queue!(self.stdout, cursor::MoveTo(0, bottom - 1)).ok();
+        let bottom = self.get_rows().unwrap_or(1);
+        queue!(self.stdout, cursor::MoveTo(0, bottom.saturating_sub(1))).ok();
         queue!(self.stdout, cursor::Show).ok();
         self.stdout.flush().ok();
     }
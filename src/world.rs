@@ -0,0 +1,241 @@
+use super::bot::{Nekobot, Others};
+use super::config::SimConfig;
+use super::contest;
+use super::immigration::ImmigrationSchedule;
+use super::lifecycle;
+use super::map::{Neighborhood, NystopiaMap, Topology};
+use super::rng::SimRng;
+use super::settings::Settings;
+use super::species::{BotSpecies, Census};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+/// How a world is written to a snapshot file
+#[derive(Clone, Copy, PartialEq)]
+pub enum SnapshotFormat {
+    /// Human-readable JSON
+    Json,
+    /// Compact binary
+    Binary,
+}
+
+impl SnapshotFormat {
+    /// JSON for files named `*.json`, binary for anything else
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".json") {
+            Self::Json
+        } else {
+            Self::Binary
+        }
+    }
+}
+
+/// Sets up a `World`, starting from the default settings, an empty configuration, an
+/// 80 by 24 map and a random seed
+pub struct WorldBuilder {
+    settings: Settings,
+    config: SimConfig,
+    cols: u16,
+    rows: u16,
+    seed: Option<u64>,
+}
+
+impl WorldBuilder {
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Plants, species, disasters and immigrations, as loaded from a TOML file
+    pub fn config(mut self, config: SimConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Size of the map, in tiles
+    pub fn size(mut self, cols: u16, rows: u16) -> Self {
+        self.cols = cols;
+        self.rows = rows;
+        self
+    }
+
+    /// Seed for the random number generator, so that a run can be reproduced
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> Result<World, Box<dyn std::error::Error>> {
+        let settings = self.settings;
+        let cols = self.cols.max(1);
+        let mut rows = self.rows.max(1);
+        // The odd-r hex layout only lines up across the top and bottom edges with an even
+        // number of rows
+        if settings.neighborhood == Neighborhood::Hex && settings.topology == Topology::Torus {
+            rows = (rows - rows % 2).max(2);
+        }
+
+        let seed = self.seed.unwrap_or_else(SimRng::random_seed);
+        info!("Seed: {}", seed);
+        let mut rng = SimRng::new(seed);
+
+        let map = NystopiaMap::new(&settings, cols, rows, &self.config, &mut rng)?;
+
+        let species = if self.config.species.is_empty() {
+            BotSpecies::from_settings(&settings)
+        } else {
+            self.config.species
+        };
+
+        let mut bots: Vec<Nekobot> = vec![];
+        // Bots are numbered in the order they come into the world
+        let mut next_id: u32 = 0;
+        for (species_id, kind) in species.iter().enumerate() {
+            for _ in 0..kind.count {
+                let bot = Nekobot::new_rand(next_id, species_id, kind, &map, &bots, &settings, &mut rng);
+                bots.push(bot);
+                next_id += 1;
+            }
+        }
+
+        let census = Census::new(&species, &bots);
+        let immigration = ImmigrationSchedule::new(&settings, self.config.immigrations, &species)?;
+
+        Ok(World {
+            settings,
+            map,
+            bots,
+            species,
+            census,
+            immigration,
+            next_id,
+            seed,
+            rng,
+            deaths: 0,
+            lifetimes: 0,
+        })
+    }
+}
+
+/// A whole simulated world: the map, the bots living on it and everything that decides
+/// what happens to them from one tick to the next
+#[derive(Serialize, Deserialize)]
+pub struct World {
+    settings: Settings,
+    map: NystopiaMap,
+    bots: Vec<Nekobot>,
+    species: Vec<BotSpecies>,
+    census: Census,
+    immigration: ImmigrationSchedule,
+    next_id: u32,
+    seed: u64,
+    rng: SimRng,
+    // Number of bots that died, and the ticks they lived in all
+    deaths: u64,
+    lifetimes: u64,
+}
+
+impl World {
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder {
+            settings: Settings::default(),
+            config: SimConfig::default(),
+            cols: 80,
+            rows: 24,
+            seed: None,
+        }
+    }
+
+    /// Takes up a world where it was left when saved to `path`, in the format its name
+    /// calls for
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        let world: Self = match SnapshotFormat::from_path(path) {
+            SnapshotFormat::Json => serde_json::from_reader(reader)?,
+            SnapshotFormat::Binary => bincode::deserialize_from(reader)?,
+        };
+        info!("Loaded tick {} from {}", world.get_tick(), path);
+        Ok(world)
+    }
+
+    /// Writes everything about the world to `path`, down to the state of its random number
+    /// generator, so that it can be loaded again and go on exactly as it would have
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        match SnapshotFormat::from_path(path) {
+            SnapshotFormat::Json => serde_json::to_writer_pretty(&mut writer, self)?,
+            SnapshotFormat::Binary => bincode::serialize_into(&mut writer, self)?,
+        }
+        writer.flush()?;
+        info!("Saved tick {} to {}", self.get_tick(), path);
+        Ok(())
+    }
+
+    /// Runs the world for one tick
+    pub fn step(&mut self) {
+        self.map.tick_map(&mut self.rng);
+        let alive: Vec<bool> = self.bots.iter().map(|bot| bot.is_alive()).collect();
+        for i in 0..self.bots.len() {
+            let (bot, mut others) = Others::split(&mut self.bots, i);
+            bot.tick(&mut self.map, &mut others, &mut self.rng);
+        }
+        // Bots die on their own turn, or on a predator's
+        for (bot, _) in self.bots.iter().zip(alive).filter(|(bot, alive)| *alive && !bot.is_alive()) {
+            self.deaths += 1;
+            self.lifetimes += bot.get_age();
+        }
+        contest::resolve(
+            &mut self.bots,
+            &mut self.map,
+            self.settings.contest,
+            self.settings.fight_cost,
+            &mut self.rng,
+        );
+        lifecycle::reap(&mut self.bots, &mut self.next_id, &self.species, &self.map, &self.settings, &mut self.rng);
+        self.immigration.arrive(&mut self.bots, &mut self.next_id, &self.species, &self.map, &self.settings, &mut self.rng);
+        self.census.update(self.map.get_tick(), &self.bots);
+    }
+
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn get_map(&self) -> &NystopiaMap {
+        &self.map
+    }
+
+    pub fn get_bots(&self) -> &[Nekobot] {
+        &self.bots
+    }
+
+    pub fn get_species(&self) -> &[BotSpecies] {
+        &self.species
+    }
+
+    pub fn get_census(&self) -> &Census {
+        &self.census
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.map.get_tick()
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of bots that have died since the start
+    pub fn get_deaths(&self) -> u64 {
+        self.deaths
+    }
+
+    /// How many ticks the bots that died lived on average, if any did
+    pub fn get_mean_lifetime(&self) -> Option<f64> {
+        match self.deaths {
+            0 => None,
+            deaths => Some(self.lifetimes as f64 / deaths as f64),
+        }
+    }
+}
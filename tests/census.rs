@@ -0,0 +1,31 @@
+use nekobots::config::SimConfig;
+use nekobots::{Settings, World};
+
+fn world(config: &str, food_prob: u8) -> World {
+    let config: SimConfig = toml::from_str(config).unwrap();
+    let settings = Settings {
+        food_prob,
+        ..Settings::default()
+    };
+    World::builder().settings(settings).config(config).seed(1).build().unwrap()
+}
+
+#[test]
+fn species_that_never_lived_dont_win() {
+    let mut world = world("[[species]]\nname = \"real\"\ncount = 5\n[[species]]\nname = \"ghost\"\ncount = 0\n", 0);
+    while world.get_bots().iter().any(|bot| bot.is_alive()) {
+        world.step();
+    }
+    assert_eq!(world.get_census().winners(), vec![0]);
+    assert!(world.get_census().to_string().contains("Winner: real"));
+}
+
+#[test]
+fn tied_species_share_the_win() {
+    let mut world = world("[[species]]\nname = \"a\"\ncount = 3\n[[species]]\nname = \"b\"\ncount = 3\n", 5);
+    for _ in 0..10 {
+        world.step();
+    }
+    assert_eq!(world.get_census().winners(), vec![0, 1]);
+    assert!(world.get_census().to_string().contains("Tie: a, b"));
+}
@@ -0,0 +1,23 @@
+use nekobots::World;
+
+fn run(seed: u64, ticks: u32) -> World {
+    let mut world = World::builder().seed(seed).build().unwrap();
+    for _ in 0..ticks {
+        world.step();
+    }
+    world
+}
+
+#[test]
+fn same_seed_same_world() {
+    let first = serde_json::to_string(&run(42, 500)).unwrap();
+    let second = serde_json::to_string(&run(42, 500)).unwrap();
+    assert!(first == second, "two runs with the same seed went different ways");
+}
+
+#[test]
+fn other_seed_other_world() {
+    let first = serde_json::to_string(&run(42, 50)).unwrap();
+    let second = serde_json::to_string(&run(43, 50)).unwrap();
+    assert!(first != second);
+}
@@ -0,0 +1,18 @@
+use nekobots::config::SimConfig;
+use nekobots::{Settings, World};
+
+#[test]
+fn immigrants_due_at_the_start_arrive_on_the_first_tick() {
+    let config: SimConfig = toml::from_str("[[immigration]]\ntick = 0\ncount = 3\n[[immigration]]\ntick = 2\n").unwrap();
+    let settings = Settings {
+        bots: 5,
+        ..Settings::default()
+    };
+    let mut world = World::builder().settings(settings).config(config).seed(1).build().unwrap();
+    world.step();
+    assert_eq!(world.get_bots().len(), 8);
+    world.step();
+    assert_eq!(world.get_bots().len(), 9);
+    world.step();
+    assert_eq!(world.get_bots().len(), 9);
+}
@@ -0,0 +1,37 @@
+use nekobots::lifecycle::DeadPolicy;
+use nekobots::recording::{Recorder, Recording, Replay};
+use nekobots::{Settings, World};
+use std::env;
+use std::fs;
+
+#[test]
+fn replay_matches_the_live_run() {
+    let path = env::temp_dir().join(format!("nekobots-{}-replay.rec", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    // Dead bots are replaced in place, so the population gets reordered all the time
+    let settings = Settings {
+        bots: 30,
+        dead: DeadPolicy::Mutate,
+        dead_time: 2,
+        ..Settings::default()
+    };
+    let mut world = World::builder().settings(settings).size(40, 20).seed(3).build().unwrap();
+    let mut recorder = Recorder::create(path, &world).unwrap();
+    let mut live = vec![];
+    for _ in 0..400 {
+        world.step();
+        recorder.record(&world).unwrap();
+        live.push(world.get_bots().iter().map(|bot| (bot.get_id(), bot.get_row(), bot.get_col())).collect::<Vec<_>>());
+    }
+    recorder.finish().unwrap();
+
+    let mut replay = Replay::new(Recording::load(path).unwrap());
+    fs::remove_file(path).unwrap();
+    for (tick, bots) in live.iter().enumerate() {
+        assert!(replay.step());
+        let replayed: Vec<_> = replay.get_bots().iter().map(|bot| (bot.get_id(), bot.get_row(), bot.get_col())).collect();
+        assert!(replayed == *bots, "bots differ on tick {}", tick + 1);
+    }
+    assert!(!replay.step());
+}
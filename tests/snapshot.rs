@@ -0,0 +1,43 @@
+use nekobots::World;
+use std::env;
+use std::fs;
+
+fn world() -> World {
+    World::builder().size(40, 20).seed(7).build().unwrap()
+}
+
+/// Steps a world halfway, saves it to `name` and loads it back, then checks that it goes
+/// on the same as a world stepped straight through
+fn resumes_exactly(name: &str) {
+    let path = env::temp_dir().join(format!("nekobots-{}-{}", std::process::id(), name));
+    let path = path.to_str().unwrap();
+
+    let mut straight = world();
+    for _ in 0..300 {
+        straight.step();
+    }
+
+    let mut saved = world();
+    for _ in 0..150 {
+        saved.step();
+    }
+    saved.save(path).unwrap();
+    let mut loaded = World::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+    for _ in 0..150 {
+        loaded.step();
+    }
+
+    assert_eq!(loaded.get_tick(), 300);
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&straight).unwrap());
+}
+
+#[test]
+fn json_snapshot_resumes_exactly() {
+    resumes_exactly("snapshot.json");
+}
+
+#[test]
+fn binary_snapshot_resumes_exactly() {
+    resumes_exactly("snapshot.bin");
+}